@@ -1,20 +1,22 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use glob::glob;
+use sat::heuristic::Branching;
 use sat::{check, naive, nonchro, parse, watch, SATSolver, Satness};
 use std::fs;
 
 pub fn test_solve_file<Solver: SATSolver>(path: &str, sat: bool) {
     for path in glob(path).unwrap() {
-        let s = fs::read_to_string(&path.unwrap()).unwrap();
+        let s = fs::read_to_string(path.unwrap()).unwrap();
         match parse::parse_file(s) {
-            Ok(cnf) => {
+            Ok((cnf, _)) => {
                 let mut solver: Solver = SATSolver::create(cnf.clone(), None);
                 let solvable = solver.solve();
                 assert_eq!(solvable.is_sat(), sat);
                 if sat {
                     match solvable {
-                        Satness::UNSAT(_) => panic!("UNSAT"),
-                        Satness::SAT(interp) => assert_eq!(check(&cnf, &interp), true),
+                        Satness::UNSAT(_) | Satness::UNSATAssuming(_) => panic!("UNSAT"),
+                        Satness::UNKNOWN(reason) => panic!("UNKNOWN: {}", reason),
+                        Satness::SAT(interp) => assert!(check(&cnf, &interp)),
                     }
                 }
             }
@@ -37,30 +39,53 @@ pub fn bench_unsat<Solver: SATSolver>() {
     test_solve_file::<Solver>(path50, false)
 }
 
+//nonchro on the UNSAT files, deciding vars with the given heuristic
+pub fn bench_branching(branching: Branching) {
+    for path in glob("tests/uuf50-218/*.cnf").unwrap() {
+        let s = fs::read_to_string(path.unwrap()).unwrap();
+        let (cnf, _) = parse::parse_file(s).unwrap();
+        let mut solver = nonchro::Solver::create(cnf, None);
+        solver.set_branching(branching);
+        assert!(!solver.solve().is_sat());
+    }
+}
+
 fn bench_naive(c: &mut Criterion) {
-    c.bench_function("naive - sat", |b| b.iter(|| bench_sat::<naive::Solver>()))
-        .bench_function("naive - unsat", |b| {
-            b.iter(|| bench_unsat::<naive::Solver>())
-        });
+    c.bench_function("naive - sat", |b| b.iter(bench_sat::<naive::Solver>))
+        .bench_function("naive - unsat", |b| b.iter(bench_unsat::<naive::Solver>));
 }
 
 fn bench_nonchro(c: &mut Criterion) {
-    c.bench_function("nonchro - sat", |b| {
-        b.iter(|| bench_sat::<nonchro::Solver>())
+    c.bench_function("nonchro - sat", |b| b.iter(bench_sat::<nonchro::Solver>))
+        .bench_function("nonchro - unsat", |b| {
+            b.iter(bench_unsat::<nonchro::Solver>)
+        });
+}
+
+fn bench_heuristics(c: &mut Criterion) {
+    c.bench_function("nonchro order - unsat", |b| {
+        b.iter(|| bench_branching(Branching::Order))
     })
-    .bench_function("nonchro - unsat", |b| {
-        b.iter(|| bench_unsat::<nonchro::Solver>())
+    .bench_function("nonchro vsids - unsat", |b| {
+        b.iter(|| bench_branching(Branching::Vsids))
+    })
+    .bench_function("nonchro evsids - unsat", |b| {
+        b.iter(|| bench_branching(Branching::Evsids))
     });
 }
 
 fn bench_watch(c: &mut Criterion) {
-    c.bench_function("watch - sat", |b| b.iter(|| bench_sat::<watch::Solver>()))
-        .bench_function("watch - unsat", |b| {
-            b.iter(|| bench_unsat::<watch::Solver>())
-        });
+    c.bench_function("watch - sat", |b| b.iter(bench_sat::<watch::Solver>))
+        .bench_function("watch - unsat", |b| b.iter(bench_unsat::<watch::Solver>));
 }
 
 criterion_group!(benches_naive, bench_naive);
 criterion_group!(benches_nonchro, bench_nonchro);
 criterion_group!(benches_watch, bench_watch);
-criterion_main!(benches_naive, benches_nonchro, benches_watch);
+criterion_group!(benches_heuristics, bench_heuristics);
+criterion_main!(
+    benches_naive,
+    benches_nonchro,
+    benches_watch,
+    benches_heuristics
+);
@@ -0,0 +1,272 @@
+use super::config::Limits;
+use super::heuristic::Rng;
+use super::nonchro::Solver;
+use super::stats::Stats;
+use super::xor::Xor;
+use super::Lit::{N, P};
+use super::Satness::{UNSATAssuming, SAT, UNKNOWN, UNSAT};
+use super::{write, Clause, Id, Interrupt, Lit, SATSolver, CNF};
+use bit_set::BitSet;
+use num_bigint::BigUint;
+use std::collections::HashMap;
+use std::time::Instant;
+
+pub const EPSILON: f64 = 0.8;
+pub const DELTA: f64 = 0.2;
+
+//Estimates the number of models with random XOR hashes, like ApproxMC.
+//Each XOR over the sampling vars cuts the models about in half, so once m
+//of them leave a cell of fewer than `threshold` models, the count is about
+//that cell's size times 2^m. The median over several hashes is within a
+//factor of 1 + epsilon of the count with probability at least 1 - delta.
+//Each hash gets an incremental nonchro solver that counts all its cells.
+//The XORs and the clauses blocking a cell's models hold only under an
+//activation var that's assumed, so they can be turned off again.
+pub struct ApproxCounter {
+    formula: CNF,
+
+    //the formula's vars, from 1, including those in no clause
+    vars: usize,
+
+    //vars in some clause
+    occurs: BitSet,
+
+    //None counts over all the vars
+    projection: Option<Vec<usize>>,
+
+    //the first var of an XOR or activation var for each solver
+    first_var: usize,
+
+    epsilon: f64,
+
+    delta: f64,
+
+    rng: Rng,
+
+    limits: Limits,
+
+    //shared by every solver
+    interrupt: Interrupt,
+
+    //of all the solvers so far
+    stats: Stats,
+}
+
+//The solver for a hash, with the vars it has made up so far
+struct Hashed {
+    solver: Solver,
+    next_var: usize,
+    //activation lits of the XORs
+    xors: Vec<Lit>,
+    //sizes of the cells of the first so many XORs, up to the threshold
+    cells: HashMap<usize, usize>,
+}
+
+impl Hashed {
+    fn fresh(&mut self) -> Id {
+        self.next_var += 1;
+        Id(self.next_var - 1)
+    }
+}
+
+//Cells this small are counted exactly
+fn threshold(epsilon: f64) -> usize {
+    let tolerance = 1.0 + epsilon / (1.0 + epsilon);
+    (1.0 + 9.84 * tolerance * (1.0 + 1.0 / epsilon).powi(2)).ceil() as usize
+}
+
+//Hashes to take the median of
+fn iterations(delta: f64) -> usize {
+    (17.0 * (3.0 / delta).log2()).ceil() as usize
+}
+
+impl ApproxCounter {
+    pub fn new(formula: CNF, vars: usize) -> ApproxCounter {
+        let occurs = formula.iter().flatten().map(|l| l.id().0).collect();
+        let first_var = vars.max(write::header_of(&formula).vars) + 1;
+        ApproxCounter {
+            formula,
+            vars,
+            occurs,
+            projection: None,
+            first_var,
+            epsilon: EPSILON,
+            delta: DELTA,
+            rng: Rng::new(0),
+            limits: Limits::default(),
+            interrupt: Interrupt::new(),
+            stats: Stats::default(),
+        }
+    }
+
+    //Hash only these vars, so cells hold their values rather than whole
+    //models. Those in no clause double the estimate instead.
+    pub fn set_projection(&mut self, vars: &[Id]) {
+        let mut vars: Vec<usize> = vars.iter().map(|&Id(id)| id).collect();
+        vars.sort_unstable();
+        vars.dedup();
+        self.projection = Some(vars);
+    }
+
+    //How far off the estimate may be, and how likely it is to be further
+    pub fn set_tolerance(&mut self, epsilon: f64, delta: f64) {
+        assert!(epsilon > 0.0, "epsilon must be positive");
+        assert!(delta > 0.0 && delta < 1.0, "delta must be between 0 and 1");
+        self.epsilon = epsilon;
+        self.delta = delta;
+    }
+
+    //The hashes are picked from this seed, so the same seed gives the same
+    //estimate
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    //The time limit is for the whole count, the others for each of the
+    //solves that counts a cell
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn interrupt_handle(&self) -> Interrupt {
+        self.interrupt.clone()
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    //The median of the hashes' estimates, or exactly the models if there
+    //are fewer than the threshold. Err if a solve was stopped.
+    pub fn count(&mut self) -> Result<BigUint, String> {
+        let mut limits = self.limits;
+        if let Some(time) = limits.time.take() {
+            let deadline = Instant::now() + time;
+            limits.deadline = Some(limits.deadline.map_or(deadline, |d| d.min(deadline)));
+        }
+        let all = match self.projection {
+            Some(ref vars) => vars.clone(),
+            None => (1..=self.vars).collect(),
+        };
+        //vars in no clause are counted by doubling rather than hashed
+        let (sampling, free): (Vec<usize>, Vec<usize>) =
+            all.into_iter().partition(|&id| self.occurs.contains(id));
+        let threshold = threshold(self.epsilon);
+
+        //no need to hash if there are few enough models to count them all
+        let mut hashed = self.hashed(limits);
+        let cell = self.cell(&mut hashed, &sampling, 0, threshold);
+        self.stats.add(hashed.solver.stats());
+        let cell = cell?;
+        if cell < threshold {
+            return Ok(BigUint::from(cell) << free.len());
+        }
+        let mut estimates = Vec::new();
+        let mut guess = 1;
+        for _ in 0..iterations(self.delta) {
+            let mut hashed = self.hashed(limits);
+            let found = self.search(&mut hashed, &sampling, threshold, guess);
+            self.stats.add(hashed.solver.stats());
+            let (cell, xors) = found?;
+            estimates.push(BigUint::from(cell) << xors);
+            guess = xors;
+        }
+        estimates.sort_unstable();
+        Ok(estimates.swap_remove(estimates.len() / 2) << free.len())
+    }
+
+    fn hashed(&self, limits: Limits) -> Hashed {
+        let mut solver = Solver::create(self.formula.clone(), None);
+        solver.set_interrupt(self.interrupt.clone());
+        solver.set_limits(limits);
+        Hashed {
+            solver,
+            next_var: self.first_var,
+            xors: Vec::new(),
+            cells: HashMap::new(),
+        }
+    }
+
+    //Add a random XOR over the sampling vars
+    fn add_xor(&mut self, hashed: &mut Hashed, sampling: &[usize]) {
+        let vars = sampling
+            .iter()
+            .filter(|_| self.rng.next_bool())
+            .map(|&id| Id(id))
+            .collect();
+        let xor = Xor::new(vars, self.rng.next_bool());
+        let act = hashed.fresh();
+        for mut cls in xor.to_cnf(&mut hashed.next_var) {
+            cls.push(N(act.clone()));
+            hashed.solver.add_clause(cls);
+        }
+        hashed.xors.push(P(act));
+    }
+
+    //The models of the cell of the first m XORs, each with different values
+    //of the sampling vars, up to the limit
+    fn cell(
+        &mut self,
+        hashed: &mut Hashed,
+        sampling: &[usize],
+        m: usize,
+        limit: usize,
+    ) -> Result<usize, String> {
+        if let Some(&cell) = hashed.cells.get(&m) {
+            return Ok(cell);
+        }
+        while hashed.xors.len() < m {
+            self.add_xor(hashed, sampling);
+        }
+        let block = hashed.fresh();
+        let mut assumptions = hashed.xors[..m].to_vec();
+        assumptions.push(P(block.clone()));
+        let mut found = 0;
+        while found < limit {
+            match hashed.solver.solve_with_assumptions(&assumptions) {
+                SAT(model) => {
+                    found += 1;
+                    let mut blocking: Clause = sampling
+                        .iter()
+                        .filter_map(|&id| {
+                            let val = model.get_val(&P(Id(id)))?;
+                            Some(if val { N(Id(id)) } else { P(Id(id)) })
+                        })
+                        .collect();
+                    blocking.push(N(block.clone()));
+                    hashed.solver.add_clause(blocking);
+                }
+                UNSAT(_) | UNSATAssuming(_) => break,
+                UNKNOWN(reason) => return Err(reason),
+            }
+        }
+        //the clauses blocking the cell's models are done with
+        hashed.solver.add_clause(vec![N(block)]);
+        hashed.cells.insert(m, found);
+        Ok(found)
+    }
+
+    //The fewest XORs of the hash that leave a cell below the threshold, and
+    //the cell's size. The more XORs the smaller the cell, so the search
+    //starts from the number the last hash needed and goes up or down.
+    fn search(
+        &mut self,
+        hashed: &mut Hashed,
+        sampling: &[usize],
+        threshold: usize,
+        guess: usize,
+    ) -> Result<(usize, usize), String> {
+        let most = sampling.len().max(1);
+        let mut m = guess.clamp(1, most);
+        if self.cell(hashed, sampling, m, threshold)? < threshold {
+            while m > 1 && self.cell(hashed, sampling, m - 1, threshold)? < threshold {
+                m -= 1;
+            }
+        } else {
+            while m < most && self.cell(hashed, sampling, m, threshold)? >= threshold {
+                m += 1;
+            }
+        }
+        Ok((self.cell(hashed, sampling, m, threshold)?, m))
+    }
+}
@@ -0,0 +1,116 @@
+use crate::config::Limits;
+use crate::stats::Stats;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+//The clock is only read every this many checks, it's slower than the rest
+pub(crate) const CLOCK_PERIOD: u64 = 64;
+
+//Stops a solver from another thread (or a signal handler). Every clone sets
+//the same flag, which stays set until it's cleared, so a solve started after
+//`interrupt` gives up straight away.
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt(Arc<AtomicBool>);
+
+impl Interrupt {
+    pub fn new() -> Interrupt {
+        Interrupt::default()
+    }
+
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn clear(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+//The limits of a solver, and what it has used of them in the current call
+//to solve
+pub(crate) struct Budget {
+    limits: Limits,
+    interrupt: Interrupt,
+    deadline: Option<Instant>,
+    //the stats when this call started
+    base: Stats,
+    checks: u64,
+}
+
+impl Budget {
+    pub(crate) fn new(limits: Limits) -> Budget {
+        Budget {
+            limits,
+            interrupt: Interrupt::new(),
+            deadline: None,
+            base: Stats::default(),
+            checks: 0,
+        }
+    }
+
+    //Handles given out before keep working
+    pub(crate) fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    //Stop on another handle instead, e.g. one several solvers share
+    pub(crate) fn set_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt = interrupt;
+    }
+
+    pub(crate) fn interrupt_handle(&self) -> Interrupt {
+        self.interrupt.clone()
+    }
+
+    //Called at the start of every solve, the limits are for each call
+    pub(crate) fn start(&mut self, stats: &Stats) {
+        let timeout = self.limits.time.map(|t| Instant::now() + t);
+        self.deadline = match (self.limits.deadline, timeout) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.base = *stats;
+        self.checks = 0;
+    }
+
+    //Why the search has to stop, if it does
+    pub(crate) fn exhausted(&mut self, stats: &Stats) -> Option<String> {
+        if self.interrupt.is_interrupted() {
+            return Some("Interrupted".to_string());
+        }
+        let limits = [
+            (
+                "Conflict",
+                stats.conflicts - self.base.conflicts,
+                self.limits.conflicts,
+            ),
+            (
+                "Decision",
+                stats.decisions - self.base.decisions,
+                self.limits.decisions,
+            ),
+            (
+                "Propagation",
+                stats.propagations - self.base.propagations,
+                self.limits.propagations,
+            ),
+        ];
+        for (what, used, limit) in limits {
+            if let Some(limit) = limit.filter(|&l| used >= l) {
+                return Some(format!("{} limit of {} reached", what, limit));
+            }
+        }
+        self.checks += 1;
+        if let Some(deadline) = self.deadline {
+            if self.checks % CLOCK_PERIOD == 1 && Instant::now() >= deadline {
+                return Some("Time limit reached".to_string());
+            }
+        }
+        None
+    }
+}
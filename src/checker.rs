@@ -0,0 +1,495 @@
+use super::proof;
+use super::watch::{add_watched, PropRes, WatchedClause};
+use super::{Clause, Id, Interp, Lit, CNF};
+use log::warn;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+use vec_map::VecMap;
+
+//A line of a DRAT proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DratStep {
+    Add(Clause),
+    Delete(Clause),
+}
+
+//A line of an LRAT proof. Clauses are numbered from 1 in the order of the
+//formula, and each added clause lists the clauses that propagate to a
+//conflict once its lits are made false.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LratStep {
+    Add {
+        id: usize,
+        cls: Clause,
+        hints: Vec<usize>,
+    },
+    Delete(Vec<usize>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    //a proof line that couldn't be read
+    Syntax(String),
+    //the underlying reader failed
+    Io(io::ErrorKind),
+    //an added clause that doesn't follow from the clauses before it
+    NotImplied(Clause),
+    //an LRAT hint or deletion naming a clause that doesn't exist
+    UnknownClause(usize),
+    //an LRAT hint naming a clause that isn't unit when it's used
+    BadHint(usize),
+    //the proof ended without deriving the empty clause
+    NoRefutation,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::Syntax(ref msg) => write!(f, "invalid proof: {}", msg),
+            ErrorKind::Io(kind) => write!(f, "couldn't read proof: {}", kind),
+            ErrorKind::NotImplied(ref cls) => {
+                write!(f, "clause `")?;
+                for lit in cls {
+                    write!(f, "{} ", lit)?;
+                }
+                write!(f, "0` isn't implied")
+            }
+            ErrorKind::UnknownClause(id) => write!(f, "no clause {}", id),
+            ErrorKind::BadHint(id) => write!(f, "hint {} isn't unit", id),
+            ErrorKind::NoRefutation => write!(f, "proof doesn't derive the empty clause"),
+        }
+    }
+}
+
+//What went wrong and the (1-based) proof step it happened at.
+//While reading a text proof, that's the line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckError {
+    pub kind: ErrorKind,
+    pub step: usize,
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at step {}", self.kind, self.step)
+    }
+}
+
+impl Error for CheckError {}
+
+pub type Check<T> = Result<T, CheckError>;
+
+fn syntax<T>(msg: &str, step: usize) -> Check<T> {
+    Err(CheckError {
+        kind: ErrorKind::Syntax(msg.to_string()),
+        step,
+    })
+}
+
+fn io_error(e: io::Error, step: usize) -> CheckError {
+    CheckError {
+        kind: ErrorKind::Io(e.kind()),
+        step,
+    }
+}
+
+fn dimacs_lit(n: i64) -> Lit {
+    let id = Id(n.unsigned_abs() as usize);
+    if n > 0 {
+        Lit::P(id)
+    } else {
+        Lit::N(id)
+    }
+}
+
+//The numbers up to the next 0, which is consumed too
+fn read_until_zero<'a, I: Iterator<Item = &'a str>>(
+    tokens: &mut I,
+    line: usize,
+) -> Check<Vec<i64>> {
+    let mut nums = Vec::new();
+    for token in tokens {
+        match token.parse::<i64>() {
+            Ok(0) => return Ok(nums),
+            Ok(n) => nums.push(n),
+            Err(_) => return syntax(&format!("`{}` isn't a number", token), line),
+        }
+    }
+    syntax("missing terminating 0", line)
+}
+
+//Read a DRAT proof, e.g. one written by `proof::Writer`
+pub fn read_drat<R: BufRead>(mut input: R, format: proof::Format) -> Check<Vec<DratStep>> {
+    match format {
+        proof::Format::Text => {
+            let mut steps = Vec::new();
+            for (i, line) in input.lines().enumerate() {
+                let line = line.map_err(|e| io_error(e, i + 1))?;
+                let mut tokens = line.split_whitespace().peekable();
+                let delete = match tokens.peek() {
+                    None | Some(&"c") => continue,
+                    Some(&"d") => {
+                        tokens.next();
+                        true
+                    }
+                    _ => false,
+                };
+                let cls = read_until_zero(&mut tokens, i + 1)?
+                    .into_iter()
+                    .map(dimacs_lit)
+                    .collect();
+                steps.push(if delete {
+                    DratStep::Delete(cls)
+                } else {
+                    DratStep::Add(cls)
+                });
+            }
+            Ok(steps)
+        }
+        proof::Format::Binary => {
+            let mut bytes = Vec::new();
+            input.read_to_end(&mut bytes).map_err(|e| io_error(e, 1))?;
+            read_binary_drat(&bytes)
+        }
+    }
+}
+
+fn read_binary_drat(bytes: &[u8]) -> Check<Vec<DratStep>> {
+    let mut steps = Vec::new();
+    let mut bytes = bytes.iter();
+    while let Some(&kind) = bytes.next() {
+        let step = steps.len() + 1;
+        let mut cls = Vec::new();
+        loop {
+            //a lit 7 bits at a time, see `proof::Writer`
+            let mut n: usize = 0;
+            let mut shift = 0;
+            loop {
+                let byte = match bytes.next() {
+                    Some(&b) => b,
+                    None => return syntax("missing terminating 0", step),
+                };
+                if shift >= usize::BITS {
+                    return syntax("literal is too large", step);
+                }
+                n |= ((byte & 0x7f) as usize) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            if n == 0 {
+                break;
+            }
+            let id = Id(n >> 1);
+            cls.push(if n & 1 == 0 { Lit::P(id) } else { Lit::N(id) });
+        }
+        steps.push(match kind {
+            b'a' => DratStep::Add(cls),
+            b'd' => DratStep::Delete(cls),
+            _ => return syntax("expected `a` or `d`", step),
+        });
+    }
+    Ok(steps)
+}
+
+//Read a text LRAT proof, as written by e.g. drat-trim with `-L`
+pub fn read_lrat<R: BufRead>(input: R) -> Check<Vec<LratStep>> {
+    let mut steps = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line.map_err(|e| io_error(e, i + 1))?;
+        let mut tokens = line.split_whitespace().peekable();
+        let id = match tokens.next() {
+            None | Some("c") => continue,
+            Some(token) => match token.parse::<usize>() {
+                Ok(id) => id,
+                Err(_) => return syntax(&format!("`{}` isn't a clause id", token), i + 1),
+            },
+        };
+        if tokens.peek() == Some(&"d") {
+            tokens.next();
+            let ids = read_until_zero(&mut tokens, i + 1)?;
+            steps.push(LratStep::Delete(ids_of(ids, i + 1)?));
+        } else {
+            let cls = read_until_zero(&mut tokens, i + 1)?
+                .into_iter()
+                .map(dimacs_lit)
+                .collect();
+            let hints = read_until_zero(&mut tokens, i + 1)?;
+            steps.push(LratStep::Add {
+                id,
+                cls,
+                hints: ids_of(hints, i + 1)?,
+            });
+        }
+    }
+    Ok(steps)
+}
+
+fn ids_of(nums: Vec<i64>, line: usize) -> Check<Vec<usize>> {
+    if nums.iter().any(|&n| n < 0) {
+        return syntax("RAT hints aren't supported", line);
+    }
+    Ok(nums.into_iter().map(|n| n as usize).collect())
+}
+
+//Check that the DRAT proof refutes the formula: each added clause has to
+//follow by reverse unit propagation (or be a resolution asymmetric
+//tautology on its first lit) from the clauses before it.
+//Like drat-trim, deleting a clause doesn't retract what it propagated at
+//the root, which only makes the check more lenient with clauses that
+//still follow from the formula.
+pub fn check_drat(formula: &CNF, proof: &[DratStep]) -> Check<()> {
+    let mut db = Drat::new();
+    for cls in formula {
+        db.add(cls.clone());
+    }
+    for (i, step) in proof.iter().enumerate() {
+        if db.refuted {
+            break;
+        }
+        match *step {
+            DratStep::Add(ref cls) => {
+                if !db.rup(cls) && !db.rat(cls) {
+                    return Err(CheckError {
+                        kind: ErrorKind::NotImplied(cls.clone()),
+                        step: i + 1,
+                    });
+                }
+                db.add(cls.clone());
+            }
+            DratStep::Delete(ref cls) => db.delete(cls),
+        }
+    }
+    if db.refuted {
+        Ok(())
+    } else {
+        Err(CheckError {
+            kind: ErrorKind::NoRefutation,
+            step: proof.len(),
+        })
+    }
+}
+
+//The clauses of a DRAT proof so far, watched for propagation
+struct Drat {
+    clss: Vec<Option<WatchedClause>>,
+    watches: VecMap<Vec<usize>>,
+    //what holds by propagation alone
+    root: Interp,
+    //set once propagation alone finds a conflict
+    refuted: bool,
+    //the live clauses with these (sorted) lits, to find what to delete
+    lookup: HashMap<Clause, Vec<usize>>,
+}
+
+fn sorted(cls: &[Lit]) -> Clause {
+    let mut key = cls.to_vec();
+    key.sort_by_key(|l| l.as_usize());
+    key.dedup();
+    key
+}
+
+impl Drat {
+    fn new() -> Drat {
+        Drat {
+            clss: Vec::new(),
+            watches: VecMap::new(),
+            root: Interp::new(),
+            refuted: false,
+            lookup: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, cls: Clause) {
+        let ind = self.clss.len();
+        let mut cls = sorted(&cls);
+        self.lookup.entry(cls.clone()).or_default().push(ind);
+        //watch lits that aren't false at the root where we can
+        cls.sort_by_key(|l| self.root.get_val(l) == Some(false));
+        let first = cls.first().map(|l| self.root.get_val(l));
+        let second = cls.get(1).map(|l| self.root.get_val(l));
+        for lit in cls.iter().take(2) {
+            add_watched(&mut self.watches, lit, ind);
+        }
+        let unit = cls.first().cloned();
+        self.clss.push(Some(WatchedClause::new(cls)));
+        match (first, second) {
+            (None, _) | (Some(Some(false)), _) => self.refuted = true,
+            (Some(None), None) | (Some(None), Some(Some(false))) => {
+                let unit = unit.unwrap();
+                let mut root = self.root.clone();
+                root.set_true(&unit);
+                if self.propagate(&mut root, vec![unit].into()) {
+                    self.refuted = true;
+                }
+                self.root = root;
+            }
+            _ => {}
+        }
+    }
+
+    fn delete(&mut self, cls: &[Lit]) {
+        match self
+            .lookup
+            .get_mut(&sorted(cls))
+            .and_then(|inds| inds.pop())
+        {
+            //the watches are dropped as they're next visited
+            Some(ind) => self.clss[ind] = None,
+            None => warn!("Ignoring deletion of a clause that isn't there: {:?}", cls),
+        }
+    }
+
+    //Does making every lit false propagate to a conflict?
+    fn rup(&mut self, cls: &[Lit]) -> bool {
+        if self.refuted {
+            return true;
+        }
+        let mut interp = self.root.clone();
+        let mut queue = VecDeque::new();
+        for lit in cls {
+            match interp.get_val(lit) {
+                Some(true) => return true,
+                Some(false) => {}
+                None => {
+                    interp.set_true(&lit.not());
+                    queue.push_back(lit.not());
+                }
+            }
+        }
+        self.propagate(&mut interp, queue)
+    }
+
+    //Is every resolvent on the first lit with the clauses it clashes with RUP?
+    fn rat(&mut self, cls: &[Lit]) -> bool {
+        let pivot = match cls.first() {
+            Some(lit) => lit.not(),
+            None => return false,
+        };
+        let resolvents: Vec<Clause> = self
+            .clss
+            .iter()
+            .flatten()
+            .filter(|c| c.lits().contains(&pivot))
+            .map(|c| {
+                let rest = c.lits().iter().filter(|&l| *l != pivot).cloned();
+                cls.iter().cloned().chain(rest).collect()
+            })
+            .collect();
+        resolvents.iter().all(|r| self.rup(r))
+    }
+
+    //Propagate the queued lits (already set in interp), true on a conflict
+    fn propagate(&mut self, interp: &mut Interp, mut queue: VecDeque<Lit>) -> bool {
+        while let Some(lit) = queue.pop_front() {
+            let clause_inds = match self.watches.get(lit.as_usize()) {
+                Some(inds) => inds.clone(),
+                None => continue,
+            };
+            let mut new_inds = Vec::new();
+            let mut conflict_seen = false;
+            for cls_ind in clause_inds {
+                if conflict_seen {
+                    new_inds.push(cls_ind);
+                    continue;
+                }
+                let bcp_res = match self.clss[cls_ind] {
+                    Some(ref mut cls) => cls.bcp(interp, &lit),
+                    //deleted
+                    None => continue,
+                };
+                if let PropRes::NewWatch(new_lit) = bcp_res {
+                    add_watched(&mut self.watches, &new_lit, cls_ind);
+                } else {
+                    new_inds.push(cls_ind);
+                    if let PropRes::Conflict = bcp_res {
+                        conflict_seen = true;
+                    } else if let PropRes::Unit(unit_lit) = bcp_res {
+                        interp.set_true(&unit_lit);
+                        queue.push_back(unit_lit);
+                    }
+                }
+            }
+            self.watches.insert(lit.as_usize(), new_inds);
+            if conflict_seen {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+//Check that the LRAT proof refutes the formula, by following the hints of
+//each added clause
+pub fn check_lrat(formula: &CNF, proof: &[LratStep]) -> Check<()> {
+    let mut clss: HashMap<usize, Clause> = (1..).zip(formula.iter().cloned()).collect();
+    for (i, step) in proof.iter().enumerate() {
+        let fail = |kind| Err(CheckError { kind, step: i + 1 });
+        match *step {
+            LratStep::Add {
+                id,
+                ref cls,
+                ref hints,
+            } => {
+                match follow_hints(&clss, cls, hints) {
+                    Ok(true) => {}
+                    Ok(false) => return fail(ErrorKind::NotImplied(cls.clone())),
+                    Err(kind) => return fail(kind),
+                }
+                if cls.is_empty() {
+                    return Ok(());
+                }
+                clss.insert(id, cls.clone());
+            }
+            LratStep::Delete(ref ids) => {
+                for id in ids {
+                    if clss.remove(id).is_none() {
+                        return fail(ErrorKind::UnknownClause(*id));
+                    }
+                }
+            }
+        }
+    }
+    Err(CheckError {
+        kind: ErrorKind::NoRefutation,
+        step: proof.len(),
+    })
+}
+
+//Make the lits of the clause false, then check each hint in turn is unit
+//until one is false. Ok(false) if the hints run out first.
+fn follow_hints(
+    clss: &HashMap<usize, Clause>,
+    cls: &[Lit],
+    hints: &[usize],
+) -> Result<bool, ErrorKind> {
+    let mut interp = Interp::new();
+    for lit in cls {
+        if interp.get_val(lit) == Some(false) {
+            //a tautology
+            return Ok(true);
+        }
+        interp.set_true(&lit.not());
+    }
+    for &hint in hints {
+        let hinted = clss.get(&hint).ok_or(ErrorKind::UnknownClause(hint))?;
+        let mut open = None;
+        for lit in hinted {
+            match interp.get_val(lit) {
+                Some(true) => return Err(ErrorKind::BadHint(hint)),
+                Some(false) => {}
+                None if open.is_none() || open == Some(lit) => open = Some(lit),
+                None => return Err(ErrorKind::BadHint(hint)),
+            }
+        }
+        match open {
+            Some(lit) => interp.set_true(lit),
+            None => return Ok(true),
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,187 @@
+use crate::heuristic::{Branching, Phase};
+use crate::nonchro::REDUCE_FIRST;
+use crate::proof;
+use crate::restart::Restart;
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+//Settings for the solvers, made with `SolverConfig::builder()` or read from
+//a file. Each solver ignores the settings it has no use for, e.g. naive and
+//watch have no restarts.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SolverConfig {
+    pub branching: Branching,
+    #[serde(deserialize_with = "restart")]
+    pub restart: Restart,
+    pub phase: Phase,
+    //conflicts before the learned clauses are first reduced, None to never
+    pub reduce_interval: Option<usize>,
+    //for random phases
+    pub seed: u64,
+    //where to write a DRAT proof, if anywhere (nonchro only)
+    pub proof: Option<ProofOutput>,
+    //how much to log: 0 only warnings, 1 info, 2 debug, 3 or more everything
+    pub verbosity: u8,
+    pub limits: Limits,
+}
+
+//Budgets for each call to solve, after which it gives up with
+//`Satness::UNKNOWN`. None is unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    pub conflicts: Option<u64>,
+    pub decisions: Option<u64>,
+    //lits set by unit propagation
+    pub propagations: Option<u64>,
+    //wall time from the start of the call, in seconds in a config file
+    #[serde(deserialize_with = "seconds")]
+    pub time: Option<Duration>,
+    //a fixed point in time, which a config file can't give
+    #[serde(skip)]
+    pub deadline: Option<Instant>,
+}
+
+fn seconds<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+    let secs = Option::<f64>::deserialize(d)?;
+    secs.map(|s| Duration::try_from_secs_f64(s).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn restart<'de, D: Deserializer<'de>>(d: D) -> Result<Restart, D::Error> {
+    let restart = Restart::deserialize(d)?;
+    restart.check().map_err(serde::de::Error::custom)?;
+    Ok(restart)
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProofOutput {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub format: proof::Format,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            branching: Branching::default(),
+            restart: Restart::default(),
+            phase: Phase::default(),
+            reduce_interval: Some(REDUCE_FIRST),
+            seed: 0,
+            proof: None,
+            verbosity: 0,
+            limits: Limits::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref e) => write!(f, "{}", e),
+            ConfigError::Toml(ref e) => write!(f, "invalid TOML: {}", e),
+            ConfigError::Json(ref e) => write!(f, "invalid JSON: {}", e),
+        }
+    }
+}
+
+impl SolverConfig {
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    //Settings missing from the file keep their defaults
+    pub fn from_toml(s: &str) -> Result<SolverConfig, ConfigError> {
+        toml::from_str(s).map_err(ConfigError::Toml)
+    }
+
+    pub fn from_json(s: &str) -> Result<SolverConfig, ConfigError> {
+        serde_json::from_str(s).map_err(ConfigError::Json)
+    }
+
+    //JSON if the file name ends in .json, TOML otherwise
+    pub fn from_file(path: &Path) -> Result<SolverConfig, ConfigError> {
+        let s = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            SolverConfig::from_json(&s)
+        } else {
+            SolverConfig::from_toml(&s)
+        }
+    }
+}
+
+//Starts from the defaults, or from `Builder::from(config)` to change some
+//settings of an existing config
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    config: SolverConfig,
+}
+
+impl From<SolverConfig> for Builder {
+    fn from(config: SolverConfig) -> Builder {
+        Builder { config }
+    }
+}
+
+impl Builder {
+    pub fn branching(mut self, branching: Branching) -> Builder {
+        self.config.branching = branching;
+        self
+    }
+
+    pub fn restart(mut self, restart: Restart) -> Builder {
+        self.config.restart = restart;
+        self
+    }
+
+    pub fn phase(mut self, phase: Phase) -> Builder {
+        self.config.phase = phase;
+        self
+    }
+
+    pub fn reduce_interval(mut self, conflicts: Option<usize>) -> Builder {
+        self.config.reduce_interval = conflicts;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Builder {
+        self.config.seed = seed;
+        self
+    }
+
+    pub fn proof<P: Into<PathBuf>>(mut self, path: P, format: proof::Format) -> Builder {
+        self.config.proof = Some(ProofOutput {
+            path: path.into(),
+            format,
+        });
+        self
+    }
+
+    pub fn verbosity(mut self, verbosity: u8) -> Builder {
+        self.config.verbosity = verbosity;
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Builder {
+        self.config.limits = limits;
+        self
+    }
+
+    pub fn build(self) -> SolverConfig {
+        self.config
+    }
+}
@@ -0,0 +1,311 @@
+use super::budget::{Budget, Interrupt};
+use super::config::Limits;
+use super::naive::get_unit;
+use super::stats::Stats;
+use super::Lit::{N, P};
+use super::{Id, Interp, Lit, CNF};
+use bit_set::BitSet;
+use num_bigint::BigUint;
+use std::collections::HashMap;
+
+//Components kept in the cache before it's cleared
+const CACHE_LIMIT: usize = 1 << 20;
+
+//Counts the models of a formula (#SAT) with the naive solver's search:
+//set a var, propagate units and try the other value after. What's left after
+//propagating is split into components that share no vars. They're counted
+//on their own and their counts multiplied, and the count of every component
+//is cached by its clauses, since the same ones come up again in other
+//branches.
+//With a projection only the distinct values of its vars are counted, so a
+//component without any of them counts 1 if it's satisfiable.
+//The search recurses a few calls deeper for every var it decides on a
+//branch, so its depth is bounded by the vars of the largest component. A
+//component of a single clause is counted directly, however long it is.
+pub struct Counter {
+    clss: CNF,
+
+    //the formula's vars, from 1, including those in no clause
+    vars: usize,
+
+    //None counts over all the vars
+    projection: Option<BitSet>,
+
+    interp: Interp,
+
+    //counts by the unassigned lits of the component's clauses, sorted
+    cache: HashMap<Vec<Vec<usize>>, BigUint>,
+
+    level: usize,
+
+    budget: Budget,
+
+    stats: Stats,
+}
+
+impl Counter {
+    pub fn new(formula: CNF, vars: usize) -> Counter {
+        Counter {
+            clss: formula,
+            vars,
+            projection: None,
+            interp: Interp::new(),
+            cache: HashMap::new(),
+            level: 0,
+            budget: Budget::new(Limits::default()),
+            stats: Stats::default(),
+        }
+    }
+
+    //Count the models by their values of these vars alone. The cached
+    //counts were of other vars, so they're dropped.
+    pub fn set_projection(&mut self, vars: &[Id]) {
+        self.projection = Some(vars.iter().map(|&Id(id)| id).collect());
+        self.cache.clear();
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.budget.set_limits(limits);
+    }
+
+    pub fn interrupt_handle(&self) -> Interrupt {
+        self.budget.interrupt_handle()
+    }
+
+    //Decisions, propagations and conflicts, and the deepest branch in
+    //max_level
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    //The exact number of models, or the limit that was reached first.
+    //Counting again after setting a projection reuses nothing.
+    pub fn count(&mut self) -> Result<BigUint, String> {
+        self.budget.start(&self.stats);
+        if self.clss.iter().any(|c| c.is_empty()) {
+            return Ok(BigUint::ZERO);
+        }
+        let clauses: Vec<usize> = (0..self.clss.len()).collect();
+        //projected vars count even if they're past the formula's
+        let scope: Vec<usize> = match self.projection {
+            Some(ref vars) => vars.iter().collect(),
+            None => (1..=self.vars).collect(),
+        };
+        self.count_branch(&clauses, &scope)
+    }
+
+    fn projected(&self, id: usize) -> bool {
+        self.projection
+            .as_ref()
+            .is_none_or(|vars| vars.contains(id))
+    }
+
+    fn satisfied(&self, ind: usize) -> bool {
+        self.clss[ind]
+            .iter()
+            .any(|l| self.interp.get_val(l) == Some(true))
+    }
+
+    fn unassigned<'a>(&'a self, ind: usize) -> impl Iterator<Item = &'a Lit> + 'a {
+        self.clss[ind]
+            .iter()
+            .filter(move |l| self.interp.get_val(l).is_none())
+    }
+
+    //Set the units of the clauses until there are none left, onto the
+    //trail. False on a conflict.
+    fn propagate(&mut self, clauses: &[usize], trail: &mut Vec<Lit>) -> bool {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &ind in clauses {
+                if let Some(unit) = get_unit(&self.clss[ind], &self.interp) {
+                    if self.interp.get_val(&unit).is_some() {
+                        self.stats.conflicts += 1;
+                        return false;
+                    }
+                    self.stats.propagations += 1;
+                    self.interp.set_true(&unit);
+                    trail.push(unit);
+                    changed = true;
+                }
+            }
+        }
+        true
+    }
+
+    //The clauses not satisfied yet, grouped by the unassigned vars they share
+    fn components(&self, clauses: &[usize]) -> Vec<Vec<usize>> {
+        fn find(parent: &mut HashMap<usize, usize>, id: usize) -> usize {
+            let up = *parent.entry(id).or_insert(id);
+            if up == id {
+                return id;
+            }
+            let root = find(parent, up);
+            parent.insert(id, root);
+            root
+        }
+        let open: Vec<usize> = clauses
+            .iter()
+            .cloned()
+            .filter(|&ind| !self.satisfied(ind))
+            .collect();
+        let mut parent = HashMap::new();
+        for &ind in &open {
+            let mut ids = self.unassigned(ind).map(|l| l.id().0);
+            if let Some(first) = ids.next() {
+                let root = find(&mut parent, first);
+                for id in ids {
+                    let other = find(&mut parent, id);
+                    parent.insert(other, root);
+                }
+            }
+        }
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+        for ind in open {
+            let id = self.unassigned(ind).next().unwrap().id().0;
+            let root = find(&mut parent, id);
+            groups.entry(root).or_default().push(ind);
+        }
+        groups.into_values().collect()
+    }
+
+    fn vars_of(&self, clauses: &[usize]) -> Vec<usize> {
+        let mut vars: Vec<usize> = clauses
+            .iter()
+            .flat_map(|&ind| self.unassigned(ind).map(|l| l.id().0))
+            .collect();
+        vars.sort_unstable();
+        vars.dedup();
+        vars
+    }
+
+    fn key(&self, clauses: &[usize]) -> Vec<Vec<usize>> {
+        let mut key: Vec<Vec<usize>> = clauses
+            .iter()
+            .map(|&ind| {
+                let mut lits: Vec<usize> = self.unassigned(ind).map(|l| l.as_usize()).collect();
+                lits.sort_unstable();
+                lits
+            })
+            .collect();
+        key.sort_unstable();
+        key
+    }
+
+    //The var in the most clauses, projected if there are any
+    fn pick(&self, clauses: &[usize], projected: bool) -> usize {
+        let mut occurs: HashMap<usize, usize> = HashMap::new();
+        for &ind in clauses {
+            for l in self.unassigned(ind) {
+                let id = l.id().0;
+                if !projected || self.projected(id) {
+                    *occurs.entry(id).or_insert(0) += 1;
+                }
+            }
+        }
+        occurs
+            .into_iter()
+            .max_by_key(|&(id, n)| (n, std::cmp::Reverse(id)))
+            .map(|(id, _)| id)
+            .unwrap()
+    }
+
+    //Propagate and count the components left, times two for every projected
+    //var in scope that's left in none of them
+    fn count_branch(&mut self, clauses: &[usize], scope: &[usize]) -> Result<BigUint, String> {
+        let mut trail = Vec::new();
+        let count = if self.propagate(clauses, &mut trail) {
+            self.count_components(clauses, scope)
+        } else {
+            Ok(BigUint::ZERO)
+        };
+        for lit in trail {
+            self.interp.unset(&lit);
+        }
+        count
+    }
+
+    fn count_components(&mut self, clauses: &[usize], scope: &[usize]) -> Result<BigUint, String> {
+        let mut count = BigUint::from(1u32);
+        let mut covered = BitSet::new();
+        for comp in self.components(clauses) {
+            let vars = self.vars_of(&comp);
+            covered.extend(vars.iter().cloned());
+            count *= self.count_component(&comp, &vars)?;
+            if count == BigUint::ZERO {
+                return Ok(count);
+            }
+        }
+        let free = scope
+            .iter()
+            .filter(|&&id| {
+                !covered.contains(id)
+                    && self.projected(id)
+                    && self.interp.get_val(&P(Id(id))).is_none()
+            })
+            .count();
+        Ok(count << free)
+    }
+
+    //A clause of k vars, p of them projected, rules out only the one
+    //assignment that falsifies all k, unless it has a var both ways
+    fn count_clause(&self, ind: usize, vars: &[usize]) -> BigUint {
+        let projected = vars.iter().filter(|&&id| self.projected(id)).count();
+        let mut lits: Vec<usize> = self.unassigned(ind).map(|l| l.as_usize()).collect();
+        lits.sort_unstable();
+        lits.dedup();
+        let all = BigUint::from(1u32) << projected;
+        if lits.len() > vars.len() || projected < vars.len() {
+            //a var both ways or one that isn't projected satisfies it
+            //whatever the projected ones are
+            all
+        } else {
+            all - 1u32
+        }
+    }
+
+    fn count_component(&mut self, clauses: &[usize], vars: &[usize]) -> Result<BigUint, String> {
+        if let Some(reason) = self.budget.exhausted(&self.stats) {
+            return Err(reason);
+        }
+        if let [ind] = *clauses {
+            return Ok(self.count_clause(ind, vars));
+        }
+        let key = self.key(clauses);
+        if let Some(count) = self.cache.get(&key) {
+            return Ok(count.clone());
+        }
+        let projected = vars.iter().any(|&id| self.projected(id));
+        let var = self.pick(clauses, projected);
+        let rest: Vec<usize> = vars.iter().cloned().filter(|&id| id != var).collect();
+        self.level += 1;
+        self.stats.max_level = self.stats.max_level.max(self.level);
+        let mut total = BigUint::ZERO;
+        for lit in [P(Id(var)), N(Id(var))] {
+            self.stats.decisions += 1;
+            self.interp.set_true(&lit);
+            let count = self.count_branch(clauses, &rest);
+            self.interp.unset(&lit);
+            let count = match count {
+                Ok(count) => count,
+                Err(reason) => {
+                    self.level -= 1;
+                    return Err(reason);
+                }
+            };
+            //without projected vars it only matters that there's a model
+            if !projected && count > BigUint::ZERO {
+                total = count;
+                break;
+            }
+            total += count;
+        }
+        self.level -= 1;
+        if self.cache.len() >= CACHE_LIMIT {
+            self.cache.clear();
+        }
+        self.cache.insert(key, total.clone());
+        Ok(total)
+    }
+}
@@ -0,0 +1,72 @@
+use std::io;
+use std::io::BufRead;
+#[cfg(any(feature = "gzip", feature = "xz", feature = "bzip2"))]
+use std::io::BufReader;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Plain,
+    Gzip,
+    Xz,
+    Bzip2,
+}
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+
+impl Format {
+    //the cargo feature needed to read this format
+    fn feature(&self) -> Option<&'static str> {
+        match *self {
+            Format::Plain => None,
+            Format::Gzip => Some("gzip"),
+            Format::Xz => Some("xz"),
+            Format::Bzip2 => Some("bzip2"),
+        }
+    }
+}
+
+//Look at the magic bytes at the start of the input without consuming them
+pub fn detect<R: BufRead>(input: &mut R) -> io::Result<Format> {
+    let start = input.fill_buf()?;
+    Ok(if start.starts_with(GZIP_MAGIC) {
+        Format::Gzip
+    } else if start.starts_with(XZ_MAGIC) {
+        Format::Xz
+    } else if start.starts_with(BZIP2_MAGIC) {
+        Format::Bzip2
+    } else {
+        Format::Plain
+    })
+}
+
+//Wrap the input in a decoder for whatever compression it uses.
+//Each format is only available when its feature is enabled,
+//otherwise reading it is an `Unsupported` error.
+pub fn decompress<'a, R: BufRead + 'a>(mut input: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    match detect(&mut input)? {
+        Format::Plain => Ok(Box::new(input)),
+        #[cfg(feature = "gzip")]
+        Format::Gzip => Ok(Box::new(BufReader::new(
+            flate2::bufread::MultiGzDecoder::new(input),
+        ))),
+        #[cfg(feature = "xz")]
+        Format::Xz => Ok(Box::new(BufReader::new(
+            xz2::bufread::XzDecoder::new_multi_decoder(input),
+        ))),
+        #[cfg(feature = "bzip2")]
+        Format::Bzip2 => Ok(Box::new(BufReader::new(
+            bzip2::bufread::MultiBzDecoder::new(input),
+        ))),
+        #[allow(unreachable_patterns)]
+        format => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "input is {:?} compressed, rebuild with `--features {}` to read it",
+                format,
+                format.feature().unwrap_or_default()
+            ),
+        )),
+    }
+}
@@ -0,0 +1,79 @@
+use super::nonchro::Solver;
+use super::Lit::{N, P};
+use super::Satness::{UNSATAssuming, SAT, UNKNOWN, UNSAT};
+use super::{Clause, Id, Interp, SATSolver};
+
+//The models of a formula, from `nonchro::Solver::models`. After each model
+//it solves again with a clause that blocks the model, or just its values of
+//the projected vars, so every model that comes out differs on them.
+//The solver keeps the blocking clauses, so it's UNSAT once they've all been
+//found.
+pub struct Models<'a> {
+    solver: &'a mut Solver,
+    projection: Option<Vec<Id>>,
+    done: bool,
+    unknown: Option<String>,
+}
+
+impl<'a> Models<'a> {
+    pub(crate) fn new(solver: &'a mut Solver, projection: Option<Vec<Id>>) -> Models<'a> {
+        //a var twice would watch the same lit twice
+        let projection = projection.map(|mut vars| {
+            vars.sort_by_key(|&Id(id)| id);
+            vars.dedup();
+            vars
+        });
+        Models {
+            solver,
+            projection,
+            done: false,
+            unknown: None,
+        }
+    }
+
+    //Why the models ran out before they were all found, e.g. a limit was
+    //reached or the solver was interrupted
+    pub fn unknown(&self) -> Option<&str> {
+        self.unknown.as_deref()
+    }
+
+    //Every var in the projection the model gives a value, flipped.
+    //Vars outside of it are left out, as are those the model doesn't need.
+    fn blocking(&self, model: &Interp) -> Clause {
+        let Interp(ref values) = *model;
+        let flip = |id: usize, val: bool| if val { N(Id(id)) } else { P(Id(id)) };
+        match self.projection {
+            Some(ref vars) => vars
+                .iter()
+                .filter_map(|&Id(id)| values.get(id).map(|&val| flip(id, val)))
+                .collect(),
+            None => values.iter().map(|(id, &val)| flip(id, val)).collect(),
+        }
+    }
+}
+
+impl<'a> Iterator for Models<'a> {
+    type Item = Interp;
+
+    fn next(&mut self) -> Option<Interp> {
+        if self.done {
+            return None;
+        }
+        match self.solver.solve() {
+            SAT(model) => {
+                let blocking = self.blocking(&model);
+                self.solver.add_clause(blocking);
+                Some(model)
+            }
+            UNSAT(_) | UNSATAssuming(_) => {
+                self.done = true;
+                None
+            }
+            UNKNOWN(reason) => {
+                self.done = true;
+                self.unknown = Some(reason);
+                None
+            }
+        }
+    }
+}
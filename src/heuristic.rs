@@ -0,0 +1,352 @@
+use crate::{Id, Lit};
+use serde::Deserialize;
+
+//How the CDCL solver picks the next var to decide
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Branching {
+    //the first unassigned lit in the order the clauses were given
+    Order,
+    //Chaff's VSIDS: vars in a conflict are bumped by 1 and every activity
+    //is halved every `VSIDS_PERIOD` conflicts
+    Vsids,
+    //MiniSat's exponential VSIDS: the bump grows by 1 / `EVSIDS_DECAY`
+    //every conflict, so older bumps count for less
+    #[default]
+    Evsids,
+}
+
+//Which value the CDCL solver gives the var it decides
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    //the value the var had last, false at first (phase saving)
+    #[default]
+    Saved,
+    False,
+    True,
+    Random,
+    //the value the var had on the longest trail without a conflict, else the
+    //saved one. Every `REPHASE_INTERVAL` conflicts the saved values are reset
+    //to the best trail so far, all false, the best again or all true, in turn.
+    Target,
+}
+
+const VSIDS_PERIOD: usize = 256;
+const EVSIDS_DECAY: f64 = 0.95;
+//rescale everything before activities overflow
+const RESCALE_LIMIT: f64 = 1e100;
+
+//Vars in a binary heap, most active first
+pub(crate) struct VarOrder {
+    branching: Branching,
+    activity: Vec<f64>,
+    heap: Vec<usize>,
+    //where each var is in the heap, if it is
+    pos: Vec<Option<usize>>,
+    inc: f64,
+    conflicts: usize,
+}
+
+impl VarOrder {
+    pub(crate) fn new(branching: Branching) -> VarOrder {
+        VarOrder {
+            branching,
+            activity: Vec::new(),
+            heap: Vec::new(),
+            pos: Vec::new(),
+            inc: 1.0,
+            conflicts: 0,
+        }
+    }
+
+    pub(crate) fn contains(&self, var: usize) -> bool {
+        self.pos.get(var).is_some_and(|p| p.is_some())
+    }
+
+    pub(crate) fn insert(&mut self, var: usize) {
+        if var >= self.pos.len() {
+            self.pos.resize(var + 1, None);
+            self.activity.resize(var + 1, 0.0);
+        }
+        if self.contains(var) {
+            return;
+        }
+        self.pos[var] = Some(self.heap.len());
+        self.heap.push(var);
+        self.sift_up(self.heap.len() - 1);
+    }
+
+    //The most active var, taken out of the heap
+    pub(crate) fn pop(&mut self) -> Option<usize> {
+        let last = self.heap.pop()?;
+        let top = if self.heap.is_empty() {
+            last
+        } else {
+            let top = self.heap[0];
+            self.heap[0] = last;
+            self.pos[last] = Some(0);
+            self.sift_down(0);
+            top
+        };
+        self.pos[top] = None;
+        Some(top)
+    }
+
+    pub(crate) fn bump(&mut self, var: usize) {
+        if var >= self.activity.len() {
+            return;
+        }
+        self.activity[var] += self.inc;
+        if self.activity[var] > RESCALE_LIMIT {
+            self.scale(1.0 / RESCALE_LIMIT);
+        }
+        if let Some(p) = self.pos[var] {
+            self.sift_up(p);
+        }
+    }
+
+    //Called once per conflict, after the bumps
+    pub(crate) fn decay(&mut self) {
+        self.conflicts += 1;
+        match self.branching {
+            Branching::Order => {}
+            Branching::Vsids => {
+                if self.conflicts.is_multiple_of(VSIDS_PERIOD) {
+                    self.scale(0.5);
+                }
+            }
+            Branching::Evsids => {
+                self.inc /= EVSIDS_DECAY;
+                if self.inc > RESCALE_LIMIT {
+                    self.scale(1.0 / RESCALE_LIMIT);
+                }
+            }
+        }
+    }
+
+    //Scaling every activity keeps the heap in order
+    fn scale(&mut self, by: f64) {
+        for a in self.activity.iter_mut() {
+            *a *= by;
+        }
+        if self.branching == Branching::Evsids {
+            self.inc *= by;
+        }
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.activity[self.heap[i]] <= self.activity[self.heap[parent]] {
+                break;
+            }
+            self.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let mut largest = i;
+            for child in [2 * i + 1, 2 * i + 2] {
+                if child < self.heap.len()
+                    && self.activity[self.heap[child]] > self.activity[self.heap[largest]]
+                {
+                    largest = child;
+                }
+            }
+            if largest == i {
+                break;
+            }
+            self.swap(i, largest);
+            i = largest;
+        }
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        self.pos[self.heap[i]] = Some(i);
+        self.pos[self.heap[j]] = Some(j);
+    }
+}
+
+const REPHASE_INTERVAL: usize = 1000;
+
+//xorshift64*, enough to pick phases reproducibly from a seed
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        //the state must never be 0, or it stays 0
+        let state = seed ^ 0x9e37_79b9_7f4a_7c15;
+        Rng(if state == 0 { 1 } else { state })
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    pub(crate) fn next_bool(&mut self) -> bool {
+        self.next_u64() >> 63 == 1
+    }
+}
+
+//Remembers values of vars to decide them the same way again
+pub(crate) struct Phases {
+    phase: Phase,
+    saved: Vec<bool>,
+    //values on the longest trail without a conflict, since the last rephase
+    target: Vec<Option<bool>>,
+    target_len: usize,
+    //and ever
+    best: Vec<Option<bool>>,
+    best_len: usize,
+    conflicts: usize,
+    rephases: usize,
+    rng: Rng,
+}
+
+impl Phases {
+    pub(crate) fn new(phase: Phase, seed: u64) -> Phases {
+        Phases {
+            phase,
+            saved: Vec::new(),
+            target: Vec::new(),
+            target_len: 0,
+            best: Vec::new(),
+            best_len: 0,
+            conflicts: 0,
+            rephases: 0,
+            rng: Rng::new(seed),
+        }
+    }
+
+    //The value to decide the var with
+    pub(crate) fn pick(&mut self, var: usize) -> bool {
+        let saved = self.saved.get(var).copied().unwrap_or(false);
+        match self.phase {
+            Phase::Saved => saved,
+            Phase::False => false,
+            Phase::True => true,
+            Phase::Random => self.rng.next_bool(),
+            Phase::Target => self.target.get(var).copied().flatten().unwrap_or(saved),
+        }
+    }
+
+    //Called for every var backtracking unassigns
+    pub(crate) fn save(&mut self, var: usize, value: bool) {
+        if var >= self.saved.len() {
+            self.saved.resize(var + 1, false);
+        }
+        self.saved[var] = value;
+    }
+
+    //Called on every conflict with the lits set below the conflict level
+    pub(crate) fn on_conflict(&mut self, trail: &[Lit]) {
+        if self.phase != Phase::Target {
+            return;
+        }
+        if trail.len() > self.target_len {
+            self.target_len = trail.len();
+            record(&mut self.target, trail);
+        }
+        if trail.len() > self.best_len {
+            self.best_len = trail.len();
+            record(&mut self.best, trail);
+        }
+        self.conflicts += 1;
+        if self.conflicts.is_multiple_of(REPHASE_INTERVAL) {
+            self.rephase();
+        }
+    }
+
+    fn rephase(&mut self) {
+        match self.rephases % 4 {
+            1 => self.saved.iter_mut().for_each(|v| *v = false),
+            3 => self.saved.iter_mut().for_each(|v| *v = true),
+            _ => {
+                if self.saved.len() < self.best.len() {
+                    self.saved.resize(self.best.len(), false);
+                }
+                for (saved, best) in self.saved.iter_mut().zip(self.best.iter()) {
+                    if let Some(value) = *best {
+                        *saved = value;
+                    }
+                }
+            }
+        }
+        self.rephases += 1;
+        self.target.clear();
+        self.target_len = 0;
+    }
+}
+
+fn record(values: &mut Vec<Option<bool>>, trail: &[Lit]) {
+    values.iter_mut().for_each(|v| *v = None);
+    for lit in trail {
+        let &Id(var) = lit.id();
+        if var >= values.len() {
+            values.resize(var + 1, None);
+        }
+        values[var] = Some(matches!(lit, Lit::P(_)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Branching, Phase, Phases, Rng, VarOrder};
+    use crate::Id;
+    use crate::Lit::{N, P};
+
+    #[test]
+    fn test_var_order() {
+        let mut order = VarOrder::new(Branching::Evsids);
+        for var in 1..6 {
+            order.insert(var);
+        }
+        order.bump(3);
+        order.decay();
+        order.bump(5);
+        order.bump(5);
+        assert_eq!(order.pop(), Some(5));
+        assert_eq!(order.pop(), Some(3));
+        order.insert(5);
+        assert_eq!(order.pop(), Some(5));
+        let mut rest = Vec::new();
+        while let Some(var) = order.pop() {
+            rest.push(var);
+        }
+        rest.sort();
+        assert_eq!(rest, vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn test_phases() {
+        let mut phases = Phases::new(Phase::Saved, 0);
+        assert!(!phases.pick(3));
+        phases.save(3, true);
+        assert!(phases.pick(3));
+        assert!(!phases.pick(4));
+
+        //the longest trail wins over what was saved
+        let mut phases = Phases::new(Phase::Target, 0);
+        phases.save(1, true);
+        phases.on_conflict(&[N(Id(1)), P(Id(2))]);
+        phases.on_conflict(&[P(Id(3))]);
+        assert!(!phases.pick(1));
+        assert!(phases.pick(2));
+        assert!(!phases.pick(3));
+    }
+
+    #[test]
+    fn test_rng_seed() {
+        //the seed that would make the state 0 still gives random numbers
+        let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+        assert_ne!(rng.next_u64(), 0);
+        assert_ne!(rng.next_u64(), rng.next_u64());
+    }
+}
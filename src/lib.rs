@@ -1,11 +1,29 @@
 use crate::Lit::{N, P};
 use crate::Satness::SAT;
+pub use budget::Interrupt;
+use config::{Limits, SolverConfig};
+use stats::{Interval, Stats};
+use std::fmt;
+use std::io;
 use vec_map::VecMap;
 
+pub mod approx;
+mod budget;
+pub mod checker;
+pub mod config;
+pub mod count;
+pub mod decompress;
+pub mod enumerate;
+pub mod heuristic;
 pub mod naive;
 pub mod nonchro;
 pub mod parse;
+pub mod proof;
+pub mod restart;
+pub mod stats;
 pub mod watch;
+pub mod write;
+pub mod xor;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Id(pub usize);
@@ -46,6 +64,16 @@ impl Lit {
     }
 }
 
+//DIMACS notation, e.g. `3` or `-3`
+impl fmt::Display for Lit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            P(Id(id)) => write!(f, "{}", id),
+            N(Id(id)) => write!(f, "-{}", id),
+        }
+    }
+}
+
 pub type Clause = Vec<Lit>;
 
 pub type CNF = Vec<Clause>;
@@ -55,6 +83,12 @@ type Map<T> = VecMap<T>;
 #[derive(Debug, Clone)]
 pub struct Interp(Map<bool>);
 
+impl Default for Interp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Interp {
     pub fn new() -> Interp {
         Interp(Map::new())
@@ -77,6 +111,13 @@ impl Interp {
             Interp(ref mut l) => l.insert(id, lit.eval(true)),
         };
     }
+
+    pub fn unset(&mut self, lit: &Lit) {
+        let &Id(id) = lit.id();
+        match *self {
+            Interp(ref mut l) => l.remove(id),
+        };
+    }
 }
 
 pub fn check_clause(cls: &Clause, interp: &Interp) -> bool {
@@ -97,19 +138,57 @@ pub fn check(form: &CNF, interp: &Interp) -> bool {
 pub enum Satness {
     SAT(Interp),
     UNSAT(String),
+    //UNSAT under the assumptions given to an incremental solver,
+    //with the subset of them that was needed to show it
+    UNSATAssuming(Vec<Lit>),
+    //gave up before finding out, e.g. because a limit was reached
+    UNKNOWN(String),
 }
 
 impl Satness {
     pub fn is_sat(&self) -> bool {
-        match *self {
-            SAT(_) => true,
-            _ => false,
-        }
+        matches!(*self, SAT(_))
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        matches!(*self, Satness::UNKNOWN(_))
     }
 }
 
 pub trait SATSolver {
     fn create(formula: CNF, interp: Option<Interp>) -> Self;
+
+    //Create the solver with these settings, ignoring those it has no use for.
+    //Fails if the proof can't be written, which only nonchro can do.
+    fn with_config(formula: CNF, interp: Option<Interp>, config: &SolverConfig) -> io::Result<Self>
+    where
+        Self: Sized,
+    {
+        if config.proof.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only the nonchro solver can write a proof",
+            ));
+        }
+        let mut solver = Self::create(formula, interp);
+        solver.set_limits(config.limits);
+        Ok(solver)
+    }
+
+    //Budgets for every call to solve from here on
+    fn set_limits(&mut self, limits: Limits);
+
+    //A handle that makes the current or next call to solve give up with
+    //`Satness::UNKNOWN`, from any thread
+    fn interrupt_handle(&self) -> Interrupt;
+
+    //Counts and times over every call to solve so far
+    fn stats(&self) -> &Stats;
+
+    //Call `hook` with the stats this often while solving, in place of any
+    //hook set before
+    fn on_progress<F: FnMut(&Stats) + Send + 'static>(&mut self, every: Interval, hook: F);
+
     fn solve(&mut self) -> Satness;
 }
 
@@ -124,8 +203,8 @@ mod tests {
         let cnf = vec![vec![P(Id(1)), N(Id(1))], vec![P(Id(2))]];
         interp.set_true(&cnf[0][0]);
         interp.set_true(&cnf[1][0]);
-        assert_eq!(check(&cnf, &interp), true);
+        assert!(check(&cnf, &interp));
         interp.set_true(&cnf[1][0].not());
-        assert_eq!(check(&cnf, &interp), false);
+        assert!(!check(&cnf, &interp));
     }
 }
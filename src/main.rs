@@ -1,59 +1,914 @@
 use docopt::Docopt;
-use sat::parse;
+use log::info;
+use num_bigint::BigUint;
+use sat::approx::ApproxCounter;
+use sat::config::{Builder, SolverConfig};
+use sat::count::Counter;
+use sat::heuristic::{Branching, Phase};
+use sat::restart::Restart;
+use sat::stats::{Interval, Stats};
+use sat::Lit::{N, P};
+use sat::{decompress, parse, proof};
 use sat::{naive, nonchro, watch};
-use sat::{SATSolver, Satness};
-use serde::Deserialize;
-use std::fs;
+use sat::{write, Id, Interp, Interrupt, Lit, SATSolver, Satness};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process;
+use std::time::{Duration, Instant};
 
 // Write the Docopt usage string.
-const USAGE: &'static str = "
-Usage: rust-sat [--solver TYPE] <inputfile>
+const USAGE: &str = "
+Usage: rust-sat [options] <inputfile>
+       rust-sat count [options] <inputfile>
        rust-sat --help
 
+Use - as the inputfile to read from stdin.
+Exits with 10 if the formula is SAT, 20 if it is UNSAT and 0 if unknown.
+count prints the number of models of the formula instead of one of them.
+
 Options:
-    --solver TYPE  Valid values: naive, watch, nonchro.
-    --help         Show this message.
+    --solver TYPE        Valid values: naive, watch, nonchro.
+    --format TYPE        Valid values: competition, text, json. [default: competition]
+    --lenient            Warn instead of failing when the body doesn't match the p line.
+    --config FILE        Read solver settings from a TOML file (or JSON if it ends
+                         in .json). The options below override it.
+    --proof FILE         Write a DRAT proof of an UNSAT result to FILE (nonchro only).
+    --proof-format TYPE  Valid values: text, binary. Default: text.
+    --branching TYPE     How nonchro picks the next var. Valid values: order, vsids,
+                         evsids. Default: evsids.
+    --restart TYPE       When nonchro restarts. Valid values: never, fixed, luby,
+                         geometric, glucose. Default: luby.
+    --restart-base N     Conflicts before the first restart. Default: 100.
+    --phase TYPE         Value nonchro gives decided vars. Valid values: saved,
+                         false, true, random, target. Default: saved.
+    --seed N             Seed for random phases and --approx hashes. Default: 0.
+    --max-conflicts N    Give up with UNKNOWN after N conflicts.
+    --max-decisions N    Give up with UNKNOWN after N decisions.
+    --max-propagations N  Give up with UNKNOWN after N propagations.
+    --time-limit SECS    Give up with UNKNOWN after SECS seconds of solving.
+    --all                Print every model (nonchro only). Vars that aren't in any
+                         clause are always given as true.
+    --max-models N       Stop after N models, at least 1. Implies --all.
+    --project VARS       Count only the distinct values of these vars, given
+                         as e.g. 1,2,5 (count only).
+    --approx             Estimate the count with random XOR hashes (count only).
+    --epsilon E          How far off the estimate may be, as a factor of
+                         1 + E. [default: 0.8]
+    --delta D            The chance the estimate is further off. [default: 0.2]
+    --stats              Print statistics of the search as c lines, as is done
+                         when interrupted. JSON output always has them.
+    --verbose            Print a table of the progress of the search to stderr
+                         every second.
+    --verbosity N        0 logs only warnings, 1 info, 2 debug, 3 everything.
+                         Default: 0.
+    --help               Show this message.
 ";
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, Copy)]
 enum SolverType {
     Naive,
     Watch,
     Nonchro,
 }
 
+impl SolverType {
+    fn name(&self) -> &'static str {
+        match *self {
+            SolverType::Naive => "naive",
+            SolverType::Watch => "watch",
+            SolverType::Nonchro => "nonchro",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+enum Format {
+    Competition,
+    Text,
+    Json,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum ProofFormat {
+    Text,
+    Binary,
+}
+
+impl ProofFormat {
+    fn format(&self) -> proof::Format {
+        match *self {
+            ProofFormat::Text => proof::Format::Text,
+            ProofFormat::Binary => proof::Format::Binary,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+enum BranchingType {
+    Order,
+    Vsids,
+    Evsids,
+}
+
+impl BranchingType {
+    fn branching(&self) -> Branching {
+        match *self {
+            BranchingType::Order => Branching::Order,
+            BranchingType::Vsids => Branching::Vsids,
+            BranchingType::Evsids => Branching::Evsids,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum RestartType {
+    Never,
+    Fixed,
+    Luby,
+    Geometric,
+    Glucose,
+}
+
+//Each restart is this much further apart than the last
+const GEOMETRIC_FACTOR: f64 = 1.5;
+const RESTART_BASE: usize = 100;
+
+impl RestartType {
+    fn policy(&self, base: usize) -> Result<Restart, String> {
+        let policy = match *self {
+            RestartType::Never => Restart::Never,
+            RestartType::Fixed => Restart::Fixed(base),
+            RestartType::Luby => Restart::Luby(base),
+            RestartType::Geometric => Restart::Geometric {
+                first: base,
+                factor: GEOMETRIC_FACTOR,
+            },
+            RestartType::Glucose => Restart::Glucose,
+        };
+        policy.check()?;
+        Ok(policy)
+    }
+}
+
+#[derive(Deserialize)]
+enum PhaseType {
+    Saved,
+    False,
+    True,
+    Random,
+    Target,
+}
+
+impl PhaseType {
+    fn phase(&self) -> Phase {
+        match *self {
+            PhaseType::Saved => Phase::Saved,
+            PhaseType::False => Phase::False,
+            PhaseType::True => Phase::True,
+            PhaseType::Random => Phase::Random,
+            PhaseType::Target => Phase::Target,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 struct Args {
+    cmd_count: bool,
     arg_inputfile: String,
     flag_solver: Option<SolverType>,
+    flag_format: Format,
+    flag_lenient: bool,
+    flag_config: Option<String>,
+    flag_proof: Option<String>,
+    flag_proof_format: Option<ProofFormat>,
+    flag_branching: Option<BranchingType>,
+    flag_restart: Option<RestartType>,
+    flag_restart_base: Option<usize>,
+    flag_phase: Option<PhaseType>,
+    flag_seed: Option<u64>,
+    flag_max_conflicts: Option<u64>,
+    flag_max_decisions: Option<u64>,
+    flag_max_propagations: Option<u64>,
+    flag_time_limit: Option<f64>,
+    flag_all: bool,
+    flag_max_models: Option<usize>,
+    flag_project: Option<String>,
+    flag_approx: bool,
+    flag_epsilon: f64,
+    flag_delta: f64,
+    flag_stats: bool,
+    flag_verbose: bool,
+    flag_verbosity: Option<u8>,
 }
 
-pub fn solve_file<Solver: SATSolver>(mut solver: Solver) {
+//The config file, if any, with the options given on top
+fn load_config(args: &Args) -> SolverConfig {
+    let config = match args.flag_config {
+        Some(ref path) => SolverConfig::from_file(Path::new(path)).unwrap_or_else(|e| {
+            eprintln!("error: couldn't read {}: {}", path, e);
+            process::exit(1);
+        }),
+        None => SolverConfig::default(),
+    };
+    let mut builder = Builder::from(config.clone());
+    if let Some(ref branching) = args.flag_branching {
+        builder = builder.branching(branching.branching());
+    }
+    if args.flag_restart.is_some() || args.flag_restart_base.is_some() {
+        let restart = args.flag_restart.unwrap_or(RestartType::Luby);
+        let base = args.flag_restart_base.unwrap_or(RESTART_BASE);
+        builder = builder.restart(restart.policy(base).unwrap_or_else(|e| {
+            eprintln!("error: invalid --restart-base {}: {}", base, e);
+            process::exit(1);
+        }));
+    }
+    if let Some(ref phase) = args.flag_phase {
+        builder = builder.phase(phase.phase());
+    }
+    if let Some(seed) = args.flag_seed {
+        builder = builder.seed(seed);
+    }
+    if let Some(verbosity) = args.flag_verbosity {
+        builder = builder.verbosity(verbosity);
+    }
+    let mut limits = config.limits;
+    limits.conflicts = args.flag_max_conflicts.or(limits.conflicts);
+    limits.decisions = args.flag_max_decisions.or(limits.decisions);
+    limits.propagations = args.flag_max_propagations.or(limits.propagations);
+    if let Some(secs) = args.flag_time_limit {
+        limits.time = Some(Duration::try_from_secs_f64(secs).unwrap_or_else(|e| {
+            eprintln!("error: invalid --time-limit {}: {}", secs, e);
+            process::exit(1);
+        }));
+    }
+    builder = builder.limits(limits);
+    let path = args
+        .flag_proof
+        .as_ref()
+        .map(|p| p.into())
+        .or_else(|| config.proof.as_ref().map(|p| p.path.clone()));
+    if let Some(path) = path {
+        let format = match args.flag_proof_format {
+            Some(format) => format.format(),
+            None => config.proof.map(|p| p.format).unwrap_or_default(),
+        };
+        builder = builder.proof(path, format);
+    }
+    builder.build()
+}
+
+//Exits with the error if the solver can't be set up as configured
+fn create<Solver: SATSolver>(cnf: sat::CNF, config: &SolverConfig) -> Solver {
+    Solver::with_config(cnf, None, config).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    })
+}
+
+//Width that `v` lines are wrapped at
+const MODEL_WIDTH: usize = 78;
+
+struct Solved {
+    solvable: Satness,
+    stats: Stats,
+    elapsed: Duration,
+    //by Ctrl-C
+    interrupted: bool,
+}
+
+//Log target of the progress table, so it can be shown without the rest
+const PROGRESS: &str = "sat::progress";
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+const PROGRESS_RULE: &str =
+    "c ===========================================================================================";
+
+fn progress_row(stats: &Stats) -> String {
+    let time = stats.decide_time + stats.propagate_time + stats.analyze_time + stats.reduce_time;
+    format!(
+        "c | {:>9.3} | {:>9} | {:>9} | {:>12} | {:>9} | {:>7.1} | {:>8} | {:>7} |",
+        time.as_secs_f64(),
+        stats.conflicts,
+        stats.decisions,
+        stats.propagations,
+        stats.learned - stats.deleted,
+        stats.avg_learned_len(),
+        stats.restarts,
+        stats.max_level
+    )
+}
+
+//Ctrl-C stops the search, which is then reported as UNKNOWN, and a
+//second one exits straight away
+fn handle_ctrlc(interrupt: &Interrupt) {
+    let handle = interrupt.clone();
+    let handler = ctrlc::set_handler(move || {
+        if handle.is_interrupted() {
+            process::exit(130);
+        }
+        handle.interrupt();
+    });
+    if let Err(e) = handler {
+        eprintln!("warning: couldn't handle Ctrl-C: {}", e);
+    }
+}
+
+//Handles Ctrl-C and starts the progress table, before a search
+fn start_search<Solver: SATSolver>(solver: &mut Solver, verbose: bool) -> Interrupt {
+    let interrupt = solver.interrupt_handle();
+    handle_ctrlc(&interrupt);
+    if verbose {
+        info!(target: PROGRESS, "{}", PROGRESS_RULE);
+        info!(
+            target: PROGRESS,
+            "c |  Time (s) | Conflicts | Decisions | Propagations |   Learned | Avg len | Restarts | Max lvl |"
+        );
+        info!(target: PROGRESS, "{}", PROGRESS_RULE);
+        solver.on_progress(
+            Interval::Time(PROGRESS_INTERVAL),
+            |stats| info!(target: PROGRESS, "{}", progress_row(stats)),
+        );
+    }
+    interrupt
+}
+
+fn end_search<Solver: SATSolver>(solver: &Solver, verbose: bool) {
+    if verbose {
+        info!(target: PROGRESS, "{}", progress_row(solver.stats()));
+        info!(target: PROGRESS, "{}", PROGRESS_RULE);
+    }
+}
+
+fn solve_file<Solver: SATSolver>(solver: &mut Solver, verbose: bool) -> Solved {
+    let interrupt = start_search(solver, verbose);
+    let start = Instant::now();
     let solvable = solver.solve();
+    end_search(solver, verbose);
+    Solved {
+        solvable,
+        stats: *solver.stats(),
+        elapsed: start.elapsed(),
+        interrupted: interrupt.is_interrupted(),
+    }
+}
+
+//Prints the models as they're found, apart from JSON which needs them all
+//first, and returns the exit code
+fn enumerate_file(solver: &mut nonchro::Solver, args: &Args, header: &parse::Header) -> i32 {
+    let interrupt = start_search(solver, args.flag_verbose);
+    let start = Instant::now();
+    if let Format::Competition = args.flag_format {
+        println!("c solver: nonchro");
+    }
+    let mut json_models = Vec::new();
+    let mut count = 0;
+    let mut models = solver.models(None);
+    for model in models
+        .by_ref()
+        .take(args.flag_max_models.unwrap_or(usize::MAX))
+    {
+        count += 1;
+        match args.flag_format {
+            Format::Competition => {
+                if count == 1 {
+                    println!("s SATISFIABLE");
+                }
+                print_model(&model, header.vars);
+            }
+            Format::Text => println!("Model {}:\n  {:?}", count, model),
+            Format::Json => {
+                let lits = model_lits(&model, header.vars).map(|l| dimacs_lit(&l));
+                json_models.push(lits.collect::<Vec<i64>>());
+            }
+        }
+    }
+    let unknown = models.unknown().map(String::from);
+    end_search(solver, args.flag_verbose);
+    let elapsed = start.elapsed();
+    let stats = args.flag_stats || interrupt.is_interrupted();
+
+    let result = if count > 0 {
+        "SAT"
+    } else if unknown.is_some() {
+        "UNKNOWN"
+    } else {
+        "UNSAT"
+    };
+    match args.flag_format {
+        Format::Competition => {
+            println!("c models: {}", count);
+            if let Some(ref reason) = unknown {
+                println!("c {}", reason);
+            }
+            println!("c solve time: {:.3}s", elapsed.as_secs_f64());
+            if stats {
+                print_stats(solver.stats());
+            }
+            match result {
+                "UNSAT" => println!("s UNSATISFIABLE"),
+                "UNKNOWN" => println!("s UNKNOWN"),
+                _ => {}
+            }
+        }
+        Format::Text => {
+            if stats {
+                print_stats(solver.stats());
+            }
+            match unknown {
+                Some(ref reason) => println!("Found {} models before stopping: {}", count, reason),
+                None if count == 0 => println!("Formula is UNSAT"),
+                None => println!("Found {} models", count),
+            }
+        }
+        Format::Json => {
+            let json = JsonResult {
+                result,
+                model: json_models.first().cloned(),
+                models: Some(json_models),
+                count: None,
+                reason: unknown.as_deref(),
+                solver: "nonchro",
+                time: elapsed.as_secs_f64(),
+                vars: header.vars,
+                clauses: header.clauses,
+                stats: JsonStats::new(solver.stats()),
+            };
+            let stdout = io::stdout();
+            serde_json::to_writer(stdout.lock(), &json).expect("couldn't write result");
+            println!();
+        }
+    }
+    match result {
+        "SAT" => 10,
+        "UNSAT" => 20,
+        _ => 0,
+    }
+}
+
+//E.g. 1,2,5
+fn parse_projection(vars: &str) -> Result<Vec<Id>, String> {
+    vars.split(',')
+        .map(|var| match var.trim().parse::<usize>() {
+            Ok(id) if id > 0 => Ok(Id(id)),
+            _ => Err(format!("invalid var {:?} in --project", var)),
+        })
+        .collect()
+}
+
+//Counts the models of the formula, or estimates how many with --approx,
+//and returns the exit code
+fn count_file(cnf: sat::CNF, args: &Args, config: &SolverConfig, header: &parse::Header) -> i32 {
+    let projection = args.flag_project.as_ref().map(|vars| {
+        parse_projection(vars).unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        })
+    });
+    let start = Instant::now();
+    let (solver, count, counted, interrupt) = if args.flag_approx {
+        let mut counter = ApproxCounter::new(cnf, header.vars);
+        counter.set_limits(config.limits);
+        counter.set_tolerance(args.flag_epsilon, args.flag_delta);
+        counter.set_seed(config.seed);
+        if let Some(ref vars) = projection {
+            counter.set_projection(vars);
+        }
+        let interrupt = counter.interrupt_handle();
+        handle_ctrlc(&interrupt);
+        ("approx", counter.count(), *counter.stats(), interrupt)
+    } else {
+        let mut counter = Counter::new(cnf, header.vars);
+        counter.set_limits(config.limits);
+        if let Some(ref vars) = projection {
+            counter.set_projection(vars);
+        }
+        let interrupt = counter.interrupt_handle();
+        handle_ctrlc(&interrupt);
+        ("count", counter.count(), *counter.stats(), interrupt)
+    };
+    let elapsed = start.elapsed();
+    let stats = args.flag_stats || interrupt.is_interrupted();
+
+    let result = match count {
+        Ok(ref n) if *n == BigUint::ZERO => "UNSAT",
+        Ok(_) => "SAT",
+        Err(_) => "UNKNOWN",
+    };
+    match args.flag_format {
+        //as in the model counting competition
+        Format::Competition => {
+            println!("c solver: {}", solver);
+            println!("c solve time: {:.3}s", elapsed.as_secs_f64());
+            if stats {
+                print_stats(&counted);
+            }
+            match count {
+                Ok(ref n) => {
+                    if result == "SAT" {
+                        println!("s SATISFIABLE");
+                    } else {
+                        println!("s UNSATISFIABLE");
+                    }
+                    let kind = if projection.is_some() { "pmc" } else { "mc" };
+                    println!("c s type {}", kind);
+                    let exact = if args.flag_approx { "approx" } else { "exact" };
+                    println!("c s {} arb int {}", exact, n);
+                }
+                Err(ref reason) => {
+                    println!("c {}", reason);
+                    println!("s UNKNOWN");
+                }
+            }
+        }
+        Format::Text => {
+            if stats {
+                print_stats(&counted);
+            }
+            match count {
+                Ok(ref n) if args.flag_approx => println!("Formula has about {} models", n),
+                Ok(ref n) => println!("Formula has {} models", n),
+                Err(ref reason) => println!("Formula is UNKNOWN: {}", reason),
+            }
+        }
+        Format::Json => {
+            let json = JsonResult {
+                result,
+                model: None,
+                models: None,
+                count: count.as_ref().ok().map(|n| n.to_string()),
+                reason: count.as_ref().err().map(|r| &r[..]),
+                solver,
+                time: elapsed.as_secs_f64(),
+                vars: header.vars,
+                clauses: header.clauses,
+                stats: JsonStats::new(&counted),
+            };
+            let stdout = io::stdout();
+            serde_json::to_writer(stdout.lock(), &json).expect("couldn't write result");
+            println!();
+        }
+    }
+    match result {
+        "SAT" => 10,
+        "UNSAT" => 20,
+        _ => 0,
+    }
+}
+
+fn print_stats(stats: &Stats) {
+    println!("c decisions:        {}", stats.decisions);
+    println!("c propagations:     {}", stats.propagations);
+    println!("c conflicts:        {}", stats.conflicts);
+    println!(
+        "c learned clauses:  {} (avg length {:.1})",
+        stats.learned,
+        stats.avg_learned_len()
+    );
+    println!("c avg backjump:     {:.2} levels", stats.avg_backjump());
+    println!("c restarts:         {}", stats.restarts);
+    println!("c max level:        {}", stats.max_level);
+    let times = [
+        ("decide", stats.decide_time),
+        ("propagate", stats.propagate_time),
+        ("analyze", stats.analyze_time),
+        ("reduce", stats.reduce_time),
+    ];
+    for (stage, time) in times {
+        println!(
+            "c {:<18}{:.3}s",
+            format!("{} time:", stage),
+            time.as_secs_f64()
+        );
+    }
+}
+
+fn print_text(solvable: &Satness) {
     print!("Formula is ");
-    match solvable {
-        Satness::UNSAT(_) => println!("UNSAT"),
-        Satness::SAT(interp) => {
+    match *solvable {
+        Satness::UNSAT(_) | Satness::UNSATAssuming(_) => println!("UNSAT"),
+        Satness::UNKNOWN(ref reason) => println!("UNKNOWN: {}", reason),
+        Satness::SAT(ref interp) => {
             println!("SAT with model:");
             println!("  {:?}", interp);
         }
     }
 }
 
+//A single JSON document for scripts driving the binary
+#[derive(Serialize)]
+struct JsonResult<'a> {
+    result: &'static str,
+    //DIMACS literals, e.g. [1, -2, 3]
+    model: Option<Vec<i64>>,
+    //every model, with --all
+    #[serde(skip_serializing_if = "Option::is_none")]
+    models: Option<Vec<Vec<i64>>>,
+    //the number of models with count, as a string since it can be any size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    count: Option<String>,
+    reason: Option<&'a str>,
+    solver: &'static str,
+    //wall time in seconds
+    time: f64,
+    vars: usize,
+    clauses: usize,
+    stats: JsonStats,
+}
+
+//`Stats` with the times in seconds
+#[derive(Serialize)]
+struct JsonStats {
+    decisions: u64,
+    propagations: u64,
+    conflicts: u64,
+    learned: u64,
+    avg_learned_len: f64,
+    avg_backjump: f64,
+    restarts: u64,
+    max_level: usize,
+    decide_time: f64,
+    propagate_time: f64,
+    analyze_time: f64,
+    reduce_time: f64,
+}
+
+impl JsonStats {
+    fn new(stats: &Stats) -> JsonStats {
+        JsonStats {
+            decisions: stats.decisions,
+            propagations: stats.propagations,
+            conflicts: stats.conflicts,
+            learned: stats.learned,
+            avg_learned_len: stats.avg_learned_len(),
+            avg_backjump: stats.avg_backjump(),
+            restarts: stats.restarts,
+            max_level: stats.max_level,
+            decide_time: stats.decide_time.as_secs_f64(),
+            propagate_time: stats.propagate_time.as_secs_f64(),
+            analyze_time: stats.analyze_time.as_secs_f64(),
+            reduce_time: stats.reduce_time.as_secs_f64(),
+        }
+    }
+}
+
+fn print_json(solved: &Solved, header: &parse::Header, solver: &SolverType) {
+    let (result, model, reason) = match solved.solvable {
+        Satness::SAT(ref interp) => {
+            let lits = model_lits(interp, header.vars).map(|l| dimacs_lit(&l));
+            ("SAT", Some(lits.collect()), None)
+        }
+        Satness::UNSAT(ref reason) => ("UNSAT", None, Some(&reason[..])),
+        Satness::UNSATAssuming(_) => ("UNSAT", None, Some("UNSAT under assumptions")),
+        Satness::UNKNOWN(ref reason) => ("UNKNOWN", None, Some(&reason[..])),
+    };
+    let json = JsonResult {
+        result,
+        model,
+        models: None,
+        count: None,
+        reason,
+        solver: solver.name(),
+        time: solved.elapsed.as_secs_f64(),
+        vars: header.vars,
+        clauses: header.clauses,
+        stats: JsonStats::new(&solved.stats),
+    };
+    let stdout = io::stdout();
+    serde_json::to_writer(stdout.lock(), &json).expect("couldn't write result");
+    println!();
+}
+
+//The `c`, `s` and `v` lines SAT competition harnesses expect
+fn print_competition(solved: &Solved, vars: usize, solver: &SolverType, stats: bool) {
+    println!("c solver: {}", solver.name());
+    println!("c solve time: {:.3}s", solved.elapsed.as_secs_f64());
+    if stats {
+        print_stats(&solved.stats);
+    }
+    match solved.solvable {
+        Satness::UNSAT(ref reason) => {
+            println!("c {}", reason);
+            println!("s UNSATISFIABLE");
+        }
+        Satness::UNSATAssuming(_) => println!("s UNSATISFIABLE"),
+        Satness::UNKNOWN(ref reason) => {
+            println!("c {}", reason);
+            println!("s UNKNOWN");
+        }
+        Satness::SAT(ref interp) => {
+            println!("s SATISFIABLE");
+            print_model(interp, vars);
+        }
+    }
+}
+
+//Every var from 1 to `vars`.
+//Vars the solver didn't need to assign are given as true.
+fn model_lits(interp: &Interp, vars: usize) -> impl Iterator<Item = Lit> + '_ {
+    (1..=vars).map(move |id| match interp.get_val(&P(Id(id))) {
+        Some(false) => N(Id(id)),
+        _ => P(Id(id)),
+    })
+}
+
+fn dimacs_lit(lit: &Lit) -> i64 {
+    match *lit {
+        P(Id(id)) => id as i64,
+        N(Id(id)) => -(id as i64),
+    }
+}
+
+//The model wrapped over as many `v` lines as needed
+fn print_model(interp: &Interp, vars: usize) {
+    let mut line = String::from("v");
+    let lits = model_lits(interp, vars).map(|l| l.to_string());
+    for lit in lits.chain(Some("0".to_string())) {
+        if line.len() + 1 + lit.len() > MODEL_WIDTH {
+            println!("{}", line);
+            line = String::from("v");
+        }
+        line.push(' ');
+        line.push_str(&lit);
+    }
+    println!("{}", line);
+}
+
+fn exit_code(solvable: &Satness) -> i32 {
+    match *solvable {
+        Satness::SAT(_) => 10,
+        Satness::UNSAT(_) | Satness::UNSATAssuming(_) => 20,
+        Satness::UNKNOWN(_) => 0,
+    }
+}
+
+fn open_input(path: &str) -> io::Result<Box<dyn BufRead>> {
+    if path == "-" {
+        decompress::decompress(io::stdin().lock())
+    } else {
+        decompress::decompress(BufReader::new(File::open(path)?))
+    }
+}
+
+fn report_parse_error(path: &str, e: &parse::ParseError) {
+    eprintln!("error: {}", e.kind);
+    if path == "-" {
+        eprintln!(" --> <stdin>:{}:{}", e.line, e.col);
+        return;
+    }
+    eprintln!(" --> {}:{}:{}", path, e.line, e.col);
+    if let Ok(Some(text)) = source_line(path, e.line) {
+        let gutter = " ".repeat(e.line.to_string().len());
+        let marker = "^".repeat(e.token.chars().count().max(1));
+        eprintln!("{} |", gutter);
+        eprintln!("{} | {}", e.line, text);
+        eprintln!("{} | {}{}", gutter, " ".repeat(e.col - 1), marker);
+    }
+}
+
+//The input was streamed through the parser, so reread the offending line.
+//None if the file is compressed, since its lines aren't the parsed ones.
+fn source_line(path: &str, line: usize) -> io::Result<Option<String>> {
+    let mut input = BufReader::new(File::open(path)?);
+    if decompress::detect(&mut input)? != decompress::Format::Plain {
+        return Ok(None);
+    }
+    input.lines().nth(line - 1).transpose()
+}
+
 pub fn main() {
     let args: Args = Docopt::new(USAGE)
         .and_then(|d| d.deserialize())
         .unwrap_or_else(|e| e.exit());
+    let config = load_config(&args);
 
-    match fs::read_to_string(&args.arg_inputfile) {
-        Ok(contents) => match parse::parse_file(contents) {
-            Ok(cnf) => match args.flag_solver {
-                Some(SolverType::Naive) => solve_file(naive::Solver::create(cnf, None)),
-                Some(SolverType::Watch) => solve_file(watch::Solver::create(cnf, None)),
-                _ => solve_file(nonchro::Solver::create(cnf, None)),
-            },
-            Err(e) => panic!("parse error: {:?}", e),
+    let level = match config.verbosity {
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+    let mut logger = if args.flag_verbose {
+        let filter = format!("{},{}=info", level, PROGRESS);
+        let mut logger =
+            env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(filter));
+        //the table is printed as is, like the c lines on stdout
+        logger.format(|buf, record| {
+            if record.target() == PROGRESS {
+                writeln!(buf, "{}", record.args())
+            } else {
+                writeln!(
+                    buf,
+                    "[{} {}] {}",
+                    record.level(),
+                    record.target(),
+                    record.args()
+                )
+            }
+        });
+        logger
+    } else {
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level))
+    };
+    logger.init();
+
+    let solver = args.flag_solver.unwrap_or(SolverType::Nonchro);
+    let all = args.flag_all || args.flag_max_models.is_some();
+    //no models would read as UNSAT
+    if args.flag_max_models == Some(0) {
+        eprintln!("error: --max-models must be at least 1");
+        process::exit(1);
+    }
+    if all && !matches!(solver, SolverType::Nonchro) {
+        eprintln!("error: --all is only supported by the nonchro solver");
+        process::exit(1);
+    }
+    //the clauses that block each model aren't in the formula
+    if all && config.proof.is_some() {
+        eprintln!("error: --all can't be used with a proof");
+        process::exit(1);
+    }
+    if args.cmd_count && (all || config.proof.is_some()) {
+        eprintln!("error: count can't be used with --all or a proof");
+        process::exit(1);
+    }
+    if (args.flag_project.is_some() || args.flag_approx) && !args.cmd_count {
+        eprintln!("error: --project and --approx are only supported by count");
+        process::exit(1);
+    }
+    if args.flag_epsilon <= 0.0 || args.flag_delta <= 0.0 || args.flag_delta >= 1.0 {
+        eprintln!("error: --epsilon must be positive and --delta between 0 and 1");
+        process::exit(1);
+    }
+    if config.proof.is_some() && !matches!(solver, SolverType::Nonchro) {
+        eprintln!("error: proofs are only supported by the nonchro solver");
+        process::exit(1);
+    }
+
+    let mode = if args.flag_lenient {
+        parse::Mode::Lenient
+    } else {
+        parse::Mode::Strict
+    };
+
+    let (cnf, header) = match open_input(&args.arg_inputfile) {
+        Ok(input) => match parse::parse_reader(input, mode) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                report_parse_error(&args.arg_inputfile, &e);
+                process::exit(1);
+            }
         },
-        Err(e) => panic!("read error: {}", e),
+        Err(e) => {
+            eprintln!("error: couldn't read {}: {}", args.arg_inputfile, e);
+            process::exit(1);
+        }
+    };
+    //the model covers every declared var, even those the body doesn't use
+    let header = parse::Header {
+        vars: header
+            .map_or(0, |h| h.vars)
+            .max(write::header_of(&cnf).vars),
+        clauses: cnf.len(),
+    };
+    if args.cmd_count {
+        process::exit(count_file(cnf, &args, &config, &header));
+    }
+
+    let solved = match solver {
+        SolverType::Naive => solve_file(
+            &mut create::<naive::Solver>(cnf, &config),
+            args.flag_verbose,
+        ),
+        SolverType::Watch => solve_file(
+            &mut create::<watch::Solver>(cnf, &config),
+            args.flag_verbose,
+        ),
+        SolverType::Nonchro if all => {
+            let mut nonchro = create::<nonchro::Solver>(cnf, &config);
+            process::exit(enumerate_file(&mut nonchro, &args, &header));
+        }
+        SolverType::Nonchro => {
+            let mut nonchro = create::<nonchro::Solver>(cnf, &config);
+            let solved = solve_file(&mut nonchro, args.flag_verbose);
+            if let Err(e) = nonchro.finish_proof() {
+                eprintln!("error: couldn't write the proof: {}", e);
+                process::exit(1);
+            }
+            solved
+        }
+    };
+    let stats = args.flag_stats || solved.interrupted;
+    match args.flag_format {
+        Format::Competition => print_competition(&solved, header.vars, &solver, stats),
+        Format::Text => {
+            if stats {
+                print_stats(&solved.stats);
+            }
+            print_text(&solved.solvable)
+        }
+        Format::Json => print_json(&solved, &header, &solver),
     }
+    process::exit(exit_code(&solved.solvable));
 }
@@ -1,4 +1,8 @@
-use super::Satness::{SAT, UNSAT};
+use super::budget::{Budget, Interrupt};
+use super::config::Limits;
+use super::stats::Stage::{Analyze, Decide, Propagate};
+use super::stats::{Clock, Interval, Progress, Stats};
+use super::Satness::{SAT, UNKNOWN, UNSAT};
 use super::{Clause, Interp, Lit, SATSolver, Satness, CNF};
 use log::{debug, info};
 use std::collections::VecDeque;
@@ -17,9 +21,17 @@ pub struct Solver {
 
     //Queue for unit propagations
     prop_queue: VecDeque<Lit>,
+
+    budget: Budget,
+
+    stats: Stats,
+
+    clock: Clock,
+
+    progress: Progress,
 }
 
-fn get_unit(c: &Clause, assigned: &Interp) -> Option<Lit> {
+pub(crate) fn get_unit(c: &Clause, assigned: &Interp) -> Option<Lit> {
     //This functions should return Some(x)
     //iff
     //  x is the only uninterpreted var
@@ -77,20 +89,17 @@ impl Solver {
     fn propagate(&mut self) {
         for c in self.clss.iter() {
             let poss_unit = get_unit(c, &self.curr_interp);
-            match poss_unit {
-                Some(u) => {
-                    let mut found = false;
-                    for p in self.prop_queue.iter() {
-                        if *p == u {
-                            found = true;
-                        }
-                    }
-                    if !found {
-                        info!("Found implied unit: {:?} in {:?}", u, c);
-                        self.prop_queue.push_back(u)
+            if let Some(u) = poss_unit {
+                let mut found = false;
+                for p in self.prop_queue.iter() {
+                    if *p == u {
+                        found = true;
                     }
                 }
-                None => {}
+                if !found {
+                    info!("Found implied unit: {:?} in {:?}", u, c);
+                    self.prop_queue.push_back(u)
+                }
             }
         }
     }
@@ -112,6 +121,7 @@ impl Solver {
                         let last_not = last.not();
                         self.set_true(&last_not);
                         self.prop_queue.clear();
+                        self.clock.switch(&mut self.stats, Some(Propagate));
                         self.propagate();
                         return true;
                     }
@@ -125,6 +135,7 @@ impl Solver {
     }
 
     fn decide_var(&mut self) -> bool {
+        self.clock.switch(&mut self.stats, Some(Decide));
         match self.find_var() {
             // here we need to pick a new var
             // because we know nothing more is constrainted
@@ -136,8 +147,11 @@ impl Solver {
                     actual_var.id(),
                     actual_var.eval(true)
                 );
+                self.stats.decisions += 1;
                 self.interp_stack
                     .push((actual_var.clone(), false, self.curr_interp.clone()));
+                self.stats.max_level = self.stats.max_level.max(self.interp_stack.len());
+                self.clock.switch(&mut self.stats, Some(Propagate));
                 self.set_true(&actual_var);
                 //info!("Propagate constraints");
                 self.propagate();
@@ -146,19 +160,8 @@ impl Solver {
             None => false,
         }
     }
-}
-
-impl SATSolver for Solver {
-    fn create(formula: CNF, interp: Option<Interp>) -> Solver {
-        Solver {
-            curr_interp: interp.unwrap_or_else(|| Interp(VecMap::new())),
-            interp_stack: Vec::new(),
-            clss: formula,
-            prop_queue: VecDeque::new(),
-        }
-    }
 
-    fn solve(&mut self) -> Satness {
+    fn search(&mut self) -> Satness {
         let units: Vec<Lit> = self
             .clss
             .iter()
@@ -176,6 +179,13 @@ impl SATSolver for Solver {
         }
 
         loop {
+            if self.progress.due(&self.stats) {
+                self.clock.flush(&mut self.stats);
+                self.progress.report(&self.stats);
+            }
+            if let Some(reason) = self.budget.exhausted(&self.stats) {
+                return UNKNOWN(reason);
+            }
             match self.prop_queue.pop_front() {
                 None => {
                     if !self.decide_var() {
@@ -187,7 +197,11 @@ impl SATSolver for Solver {
                         //just reverse the most recent non post_conflicted assignment
                         //reversing also all the propagated stuff
                         info!("Conflict at {:?}", constr_lit);
+                        self.stats.conflicts += 1;
+                        self.clock.switch(&mut self.stats, Some(Analyze));
+                        let level = self.interp_stack.len();
                         let res = self.backtrack();
+                        self.stats.backjumped += (level - self.interp_stack.len()) as u64;
                         if !res {
                             let reason = format!("Found conflict with {:?}", constr_lit.id());
                             return UNSAT(reason);
@@ -199,6 +213,7 @@ impl SATSolver for Solver {
                             constr_lit.id(),
                             constr_lit.eval(true)
                         );
+                        self.stats.propagations += 1;
                         self.set_true(&constr_lit);
                         self.propagate();
                     }
@@ -209,6 +224,46 @@ impl SATSolver for Solver {
     }
 }
 
+impl SATSolver for Solver {
+    fn create(formula: CNF, interp: Option<Interp>) -> Solver {
+        Solver {
+            curr_interp: interp.unwrap_or_else(|| Interp(VecMap::new())),
+            interp_stack: Vec::new(),
+            clss: formula,
+            prop_queue: VecDeque::new(),
+            budget: Budget::new(Limits::default()),
+            stats: Stats::default(),
+            clock: Clock::new(),
+            progress: Progress::new(),
+        }
+    }
+
+    fn set_limits(&mut self, limits: Limits) {
+        self.budget.set_limits(limits);
+    }
+
+    fn interrupt_handle(&self) -> Interrupt {
+        self.budget.interrupt_handle()
+    }
+
+    fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    fn on_progress<F: FnMut(&Stats) + Send + 'static>(&mut self, every: Interval, hook: F) {
+        self.progress.set(every, Box::new(hook));
+    }
+
+    fn solve(&mut self) -> Satness {
+        self.budget.start(&self.stats);
+        self.progress.start(&self.stats);
+        self.clock.switch(&mut self.stats, Some(Propagate));
+        let result = self.search();
+        self.clock.switch(&mut self.stats, None);
+        result
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::Id;
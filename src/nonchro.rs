@@ -1,11 +1,23 @@
 use self::Safety::{Conflict, Safe};
+use super::budget::{Budget, Interrupt};
+use super::config::{Limits, SolverConfig};
+use super::enumerate::Models;
+use super::heuristic::{Branching, Phase, Phases, VarOrder};
+use super::proof;
+use super::restart::{Restart, Restarts};
+use super::stats::Stage::{Analyze, Decide, Propagate, Reduce};
+use super::stats::{Clock, Interval, Progress, Stats};
+use super::Lit::{N, P};
 use super::Satness;
-use super::Satness::{SAT, UNSAT};
+use super::Satness::{UNSATAssuming, SAT, UNKNOWN, UNSAT};
 use super::{Clause, Id, Interp, Lit, Map, SATSolver, CNF};
 use bit_set::BitSet;
 use log::info;
 use std::cmp::max;
 use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
 use vec_map::Entry::{Occupied, Vacant};
 use vec_map::VecMap;
 
@@ -95,7 +107,7 @@ fn add_watched(watches: &mut VecMap<Vec<usize>>, lit: &Lit, ind: usize) {
 }
 
 fn get_impl_clause<'a>(
-    cls: &'a Clause,
+    cls: &'a [Lit],
     confl_lit: Option<&'a Lit>,
 ) -> Box<dyn Iterator<Item = Lit> + 'a> {
     match confl_lit {
@@ -118,11 +130,31 @@ type DecInfo = (DecLevel, Implicant);
 type WatcherList = VecMap<Vec<usize>>;
 type WatchedFormula = Vec<WatchedClause>;
 
-pub struct Solver {
-    //Interpretation stack
-    //the var we set to true, whether this was after a conflict and the interp
-    interp_stack: Vec<(Lit, Interp, Map<DecInfo>)>,
+//How good a learned clause still in `clss` is
+struct Learnt {
+    //literal block distance, how many levels its lits were set at
+    lbd: usize,
+    //bumped every time it's used in a conflict
+    activity: f64,
+}
+
+//Learned clauses with an LBD this low are kept for good
+const GLUE_LBD: usize = 2;
+//Conflicts before the first reduction, and how many more for each after
+pub(crate) const REDUCE_FIRST: usize = 2000;
+const REDUCE_INC: usize = 300;
+const CLAUSE_DECAY: f64 = 0.999;
+const CLAUSE_RESCALE: f64 = 1e20;
+
+//Where a clause came from
+enum Provenance {
+    //given by the user, with its index in the order clauses were given
+    Original(usize),
+    //learned from these clauses
+    Learned(Vec<usize>),
+}
 
+pub struct Solver {
     //Current interpretation
     interp: Interp,
 
@@ -136,6 +168,70 @@ pub struct Solver {
 
     //tracking decision level and implicants
     track: Map<DecInfo>,
+
+    //lits in the order they were set, and where each level starts in it,
+    //so backtracking only has to unset the tail
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>,
+
+    //the first levels of the trail hold the assumptions of the current call
+    assumption_lvls: usize,
+
+    //assumptions responsible for the last UNSAT, if it was only UNSAT under them
+    failed: Option<Vec<Lit>>,
+
+    //set once the clauses themselves are found UNSAT, whatever is assumed
+    root_conflict: bool,
+
+    //added clauses that are unit at level 0, to propagate on the next solve
+    root_units: Vec<(Lit, usize)>,
+
+    //the provenance of each clause, if we're tracking the unsat core
+    provenance: Option<Vec<Provenance>>,
+
+    //number of clauses the user has given
+    originals: usize,
+
+    //original clauses involved in the last UNSAT
+    core: Option<Vec<usize>>,
+
+    //number of clauses learned so far
+    learned: usize,
+
+    //where learned clauses are written, if we're logging a proof
+    proof: Option<proof::Writer<Box<dyn Write>>>,
+
+    //how the next var to decide is picked
+    branching: Branching,
+
+    //unassigned vars by activity, when not branching in order
+    order: VarOrder,
+
+    //which value a decided var gets
+    phases: Phases,
+
+    //when to restart
+    restarts: Restarts,
+
+    //scores of the learned clauses that haven't been deleted, by index
+    learnts: VecMap<Learnt>,
+
+    //bump for clause activity, which grows as older bumps decay
+    cla_inc: f64,
+
+    //conflicts between reductions of the learned clauses, None to never reduce
+    reduce_interval: Option<usize>,
+
+    //conflicts since the last reduction
+    reduce_conflicts: usize,
+
+    budget: Budget,
+
+    stats: Stats,
+
+    clock: Clock,
+
+    progress: Progress,
 }
 
 enum Safety {
@@ -145,84 +241,232 @@ enum Safety {
 
 impl Solver {
     fn level(&self) -> DecLevel {
-        DecLevel(self.interp_stack.len())
+        DecLevel(self.trail_lim.len())
+    }
+
+    fn find_var(&mut self) -> Option<Lit> {
+        let var = if self.branching == Branching::Order {
+            self.clss
+                .iter()
+                .flat_map(|x| x.cls.iter())
+                .find(|x| self.interp.get_val(x).is_none())
+                .map(|x| x.id().0)
+        } else {
+            let mut found = None;
+            while let Some(var) = self.order.pop() {
+                if self.interp.get_val(&P(Id(var))).is_none() {
+                    found = Some(var);
+                    break;
+                }
+            }
+            found
+        }?;
+        Some(if self.phases.pick(var) {
+            P(Id(var))
+        } else {
+            N(Id(var))
+        })
     }
 
-    fn find_var(&self) -> Option<Lit> {
-        for x in self.clss.iter().flat_map(|x| x.cls.iter()) {
-            if self.interp.get_val(x).is_none() {
-                return Some(x.clone());
+    //Use this heuristic from the next decision on
+    pub fn set_branching(&mut self, branching: Branching) {
+        self.branching = branching;
+        self.order = VarOrder::new(branching);
+        if branching != Branching::Order {
+            let vars: BitSet = self
+                .clss
+                .iter()
+                .flat_map(|c| c.cls.iter())
+                .map(|l| l.id().0)
+                .collect();
+            for var in vars.iter() {
+                //assigned ones go back on when backtracking unsets them
+                if self.interp.get_val(&P(Id(var))).is_none() {
+                    self.order.insert(var);
+                }
             }
         }
-        None
     }
 
     fn set_true(&mut self, lit: &Lit, cause: Implicant) {
         self.interp.set_true(lit);
+        self.trail.push(lit.clone());
         let dec_lvl = self.level();
         let &Id(id) = lit.id();
         self.track.insert(id, (dec_lvl, cause));
     }
 
-    fn add_clause(&mut self, this_lit: &Lit, cls: Clause) -> usize {
+    fn learn_clause(&mut self, this_lit: &Lit, cls: Clause, antecedents: Vec<usize>) -> usize {
         let new_ind: usize = self.clss.len();
+        self.learned += 1;
+        //this_lit is about to be set at a level of its own
+        let lbd = self.lbd(&cls) + 1;
+        self.learnts.insert(
+            new_ind,
+            Learnt {
+                lbd,
+                activity: self.cla_inc,
+            },
+        );
+        if let Some(ref mut provenance) = self.provenance {
+            provenance.push(Provenance::Learned(antecedents));
+        }
+        self.log_lemma(&cls);
         let mut indices = (0, 0);
         let mut max_dec = 0;
-        for i in 0..cls.len() {
-            let lit = &cls[i];
+        for (i, lit) in cls.iter().enumerate() {
             let &Id(id) = lit.id();
             if lit == this_lit {
                 indices.0 = i;
-            } else {
-                self.track.get(id).map(|&(DecLevel(dec), _)| {
-                    if dec > max_dec {
-                        max_dec = dec;
-                        indices.1 = i;
-                    }
-                });
+            } else if let Some(&(DecLevel(dec), _)) = self.track.get(id) {
+                if dec > max_dec {
+                    max_dec = dec;
+                    indices.1 = i;
+                }
             }
         }
         add_watched(&mut self.watches, &cls[indices.0], new_ind);
-        add_watched(&mut self.watches, &cls[indices.1], new_ind);
-        self.clss.push(WatchedClause {
-            indices: indices,
-            cls: cls,
-        });
+        if indices.1 != indices.0 {
+            add_watched(&mut self.watches, &cls[indices.1], new_ind);
+        }
+        self.clss.push(WatchedClause { indices, cls });
         new_ind
     }
 
-    fn trace_conflict(&self, curr_dec_lvl: &DecLevel, confl: &Clause) -> (Clause, DecLevel) {
-        let mut learned = Vec::new();
-        let mut back_lvl = DecLevel(0);
-
-        let mut lit_queue: VecDeque<Lit> = get_impl_clause(confl, None).collect();
+    fn lvl_of(&self, lit: &Lit) -> usize {
+        self.track
+            .get(lit.id().0)
+            .map_or(0, |&(DecLevel(lvl), _)| lvl)
+    }
 
+    //First UIP analysis: resolve the conflict clause with the implicants of
+    //its lits at the conflict level, newest on the trail first, until only
+    //one is left. Gives the learned clause, with the negated UIP first, and
+    //the level it's unit at.
+    fn analyze(
+        &self,
+        confl: usize,
+        confl_lvl: usize,
+        used: &mut Vec<usize>,
+        bumped: &mut BitSet,
+    ) -> (Clause, DecLevel) {
         let mut seen = BitSet::new();
+        let mut learned = vec![];
+        //false at the root, so left out of the clause
+        let mut at_root = Vec::new();
+        //lits at the conflict level still to be resolved away
+        let mut open = 0;
+        let mut cls_ind = confl;
+        let mut resolved: Option<Lit> = None;
+        let mut index = self.trail.len();
+        let uip = loop {
+            for lit in self.clss[cls_ind].cls.iter() {
+                let &Id(id) = lit.id();
+                if resolved.as_ref() == Some(lit) || seen.contains(id) {
+                    continue;
+                }
+                seen.insert(id);
+                match self.lvl_of(lit) {
+                    0 => at_root.push(lit.clone()),
+                    lvl if lvl == confl_lvl => open += 1,
+                    _ => learned.push(lit.clone()),
+                }
+            }
+            let lit = loop {
+                index -= 1;
+                if seen.contains(self.trail[index].id().0) {
+                    break self.trail[index].clone();
+                }
+            };
+            open -= 1;
+            if open == 0 {
+                break lit;
+            }
+            let cause = self.track[lit.id().0]
+                .1
+                .expect("only the decision of a level has no implicant");
+            used.push(cause);
+            cls_ind = cause;
+            resolved = Some(lit);
+        };
+        bumped.union_with(&seen);
 
-        while let Some(lit) = lit_queue.pop_front() {
+        //drop the lits whose implicants only lead back to the rest
+        let in_clause: BitSet = learned.iter().map(|l| l.id().0).collect();
+        let lvls: BitSet = learned.iter().map(|l| self.lvl_of(l)).collect();
+        let mut removable = BitSet::new();
+        let mut poisoned = BitSet::new();
+        learned.retain(|lit| {
+            !self.redundant(
+                lit,
+                &in_clause,
+                &lvls,
+                (&mut removable, &mut poisoned),
+                used,
+                &mut at_root,
+            )
+        });
+
+        if self.provenance.is_some() {
+            self.implicants(&at_root, used);
+        }
+        let back_lvl = learned.iter().map(|l| self.lvl_of(l)).max().unwrap_or(0);
+        learned.insert(0, uip.not());
+        (learned, DecLevel(back_lvl))
+    }
+
+    //Whether this false lit of the learned clause follows from the others,
+    //going back through implicants. Vars already found to be (or not to be)
+    //removable are cached across calls.
+    fn redundant(
+        &self,
+        lit: &Lit,
+        in_clause: &BitSet,
+        lvls: &BitSet,
+        (removable, poisoned): (&mut BitSet, &mut BitSet),
+        used: &mut Vec<usize>,
+        at_root: &mut Vec<Lit>,
+    ) -> bool {
+        let mut stack = vec![lit.not()];
+        let mut visited = BitSet::new();
+        let mut causes = Vec::new();
+        let mut roots = Vec::new();
+        while let Some(lit) = stack.pop() {
             let &Id(id) = lit.id();
-            if let Some(&(ref dec_lvl, cause_)) = self.track.get(id) {
-                if seen.contains(id) {
+            let cause = match self.track.get(id) {
+                Some(&(_, Some(cause))) => cause,
+                _ => {
+                    poisoned.insert(lit.id().0);
+                    return false;
+                }
+            };
+            causes.push(cause);
+            for next in get_impl_clause(&self.clss[cause].cls, Some(&lit)) {
+                let &Id(next_id) = next.id();
+                if in_clause.contains(next_id)
+                    || removable.contains(next_id)
+                    || !visited.insert(next_id)
+                {
                     continue;
-                } else {
-                    seen.insert(id);
                 }
-
-                if let Some(cause) = cause_ {
-                    if dec_lvl == curr_dec_lvl {
-                        let next_cause = &self.clss[cause].cls;
-                        for lit in get_impl_clause(next_cause, Some(&lit)) {
-                            lit_queue.push_back(lit);
-                        }
-                        //skip below 2 statements
-                        continue;
-                    }
+                let lvl = self.lvl_of(&next);
+                if lvl == 0 {
+                    roots.push(next.not());
+                    continue;
                 }
-                learned.push(lit.not());
-                back_lvl = max(back_lvl, *dec_lvl);
+                //a lit at a level the clause doesn't have leads to a decision
+                //that isn't in it
+                if poisoned.contains(next_id) || !lvls.contains(lvl) {
+                    poisoned.insert(lit.id().0);
+                    return false;
+                }
+                stack.push(next);
             }
         }
-        (learned, back_lvl)
+        removable.union_with(&visited);
+        used.extend(causes);
+        at_root.extend(roots);
+        true
     }
 
     fn check_watchers(&mut self, lit: Lit) -> Implicant {
@@ -255,6 +499,7 @@ impl Solver {
     }
 
     fn decide_var(&mut self, lit: Option<Lit>) -> Option<Safety> {
+        self.clock.switch(&mut self.stats, Some(Decide));
         lit.or_else(|| self.find_var()).map(|decision| {
             // here we need to pick a new var
             // because we know nothing more is constrainted
@@ -265,55 +510,485 @@ impl Solver {
                 decision.id(),
                 decision.eval(true)
             );
-            self.interp_stack
-                .push((decision.clone(), self.interp.clone(), self.track.clone()));
+            self.stats.decisions += 1;
+            self.trail_lim.push(self.trail.len());
+            self.stats.max_level = self.stats.max_level.max(self.trail_lim.len());
+            self.clock.switch(&mut self.stats, Some(Propagate));
             self.process(decision, None)
         })
     }
 
-    fn backtrack(&mut self, cause: Clause, DecLevel(back_lvl): DecLevel) -> Safety {
-        self.interp_stack.truncate(back_lvl);
-        match self.interp_stack.pop() {
-            //here use the learned clause as a cause
-            Some((last, interp, trace)) => {
-                let last_not = last.not();
-                self.interp = interp;
-                self.track = trace;
-                let new_ind = self.add_clause(&last_not, cause);
-                self.interp_stack
-                    .push((last.not(), self.interp.clone(), self.track.clone()));
-                self.prop_queue.clear();
-                self.process(last_not, Some(new_ind))
+    //Follow the implicants of these false lits back to the decisions they
+    //came from, i.e. the assumptions that made them false
+    fn analyze_final(&self, falsified: &[Lit]) -> Vec<Lit> {
+        let mut failed = Vec::new();
+        let mut lit_queue: VecDeque<Lit> = get_impl_clause(falsified, None).collect();
+        let mut seen = BitSet::new();
+        while let Some(lit) = lit_queue.pop_front() {
+            let &Id(id) = lit.id();
+            if seen.contains(id) {
+                continue;
             }
-            None => {
-                info!("Hit root level, UNSAT");
-                Conflict
+            seen.insert(id);
+            match self.track.get(id) {
+                Some(&(DecLevel(0), _)) | None => {}
+                Some(&(_, Some(cause))) => {
+                    lit_queue.extend(get_impl_clause(&self.clss[cause].cls, Some(&lit)))
+                }
+                Some(&(_, None)) => failed.push(lit),
+            }
+        }
+        failed
+    }
+
+    //The original clauses needed to show these lits are false, given the
+    //clauses they were derived from. None unless we're tracking the core.
+    fn find_core(&self, mut clauses: Vec<usize>, falsified: &[Lit]) -> Option<Vec<usize>> {
+        let provenance = self.provenance.as_ref()?;
+        self.implicants(falsified, &mut clauses);
+        //then back through the learned clauses to the originals
+        let mut core = Vec::new();
+        let mut seen = BitSet::new();
+        while let Some(ind) = clauses.pop() {
+            if seen.contains(ind) {
+                continue;
+            }
+            seen.insert(ind);
+            match provenance[ind] {
+                Provenance::Original(orig) => core.push(orig),
+                Provenance::Learned(ref antecedents) => clauses.extend(antecedents),
+            }
+        }
+        core.sort_unstable();
+        Some(core)
+    }
+
+    //The implicants of these false lits, at every level
+    fn implicants(&self, falsified: &[Lit], clauses: &mut Vec<usize>) {
+        let mut lit_queue: VecDeque<Lit> = get_impl_clause(falsified, None).collect();
+        let mut seen = BitSet::new();
+        while let Some(lit) = lit_queue.pop_front() {
+            let &Id(id) = lit.id();
+            if seen.contains(id) {
+                continue;
+            }
+            seen.insert(id);
+            if let Some(&(_, Some(cause))) = self.track.get(id) {
+                clauses.push(cause);
+                lit_queue.extend(get_impl_clause(&self.clss[cause].cls, Some(&lit)));
+            }
+        }
+    }
+
+    //Record where clauses come from so `unsat_core` can be used after an
+    //UNSAT result. Has to be called before the first solve.
+    pub fn track_core(&mut self) {
+        assert_eq!(
+            self.learned, 0,
+            "core tracking has to start before any clauses are learned"
+        );
+        self.provenance = Some((0..self.clss.len()).map(Provenance::Original).collect());
+    }
+
+    //Indices of the original clauses (in the order they were given to
+    //`create` and then `add_clause`) involved in the last UNSAT result,
+    //when tracking the core
+    pub fn unsat_core(&self) -> Option<&[usize]> {
+        self.core.as_deref()
+    }
+
+    //Write a DRAT proof of every clause learned from here on to `out`, so
+    //an UNSAT result can be checked with e.g. drat-trim against the clauses
+    //given to `create` and `add_clause`. Has to be called before the first
+    //solve, and `finish_proof` called once done.
+    pub fn log_proof<W: Write + 'static>(&mut self, out: W, format: proof::Format) {
+        assert_eq!(
+            self.learned, 0,
+            "proof logging has to start before any clauses are learned"
+        );
+        self.proof = Some(proof::Writer::new(Box::new(out), format));
+    }
+
+    //Flush the proof and stop logging it, with the first error hit while
+    //writing it if there was one
+    pub fn finish_proof(&mut self) -> io::Result<()> {
+        match self.proof.take() {
+            Some(proof) => proof.finish().map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    fn log_lemma(&mut self, cls: &[Lit]) {
+        if let Some(ref mut proof) = self.proof {
+            proof.add(cls);
+        }
+    }
+
+    //Undo every decision, leaving only what holds at level 0
+    fn backtrack_to_root(&mut self) {
+        self.backtrack_to(0);
+        self.assumption_lvls = 0;
+    }
+
+    //Undo every decision above this level
+    fn backtrack_to(&mut self, lvl: usize) {
+        if self.trail_lim.len() > lvl {
+            for lit in self.trail.drain(self.trail_lim[lvl]..) {
+                let &Id(id) = lit.id();
+                self.interp.unset(&lit);
+                self.track.remove(id);
+                self.phases.save(id, matches!(lit, P(_)));
+                if self.branching != Branching::Order {
+                    self.order.insert(id);
+                }
+            }
+            self.trail_lim.truncate(lvl);
+        }
+        self.prop_queue.clear();
+    }
+
+    //Use this to pick the value of decided vars from the next decision on,
+    //with random values drawn from `seed`
+    pub fn set_phase(&mut self, phase: Phase, seed: u64) {
+        self.phases = Phases::new(phase, seed);
+    }
+
+    //Use these settings from here on, apart from the proof, which can only
+    //be set up by `with_config` or `log_proof` before solving
+    pub fn configure(&mut self, config: &SolverConfig) {
+        self.set_branching(config.branching);
+        self.set_restart(config.restart);
+        self.set_phase(config.phase, config.seed);
+        self.set_reduce_interval(config.reduce_interval);
+        self.set_limits(config.limits);
+    }
+
+    //Use this restart policy from the next conflict on.
+    //Learned clauses are kept when restarting, as are the assumptions.
+    pub fn set_restart(&mut self, policy: Restart) {
+        self.restarts = Restarts::new(policy);
+    }
+
+    fn bump_clauses(&mut self, used: &[usize]) {
+        for &ind in used {
+            if let Some(learnt) = self.learnts.get_mut(ind) {
+                learnt.activity += self.cla_inc;
+                if learnt.activity > CLAUSE_RESCALE {
+                    for (_, learnt) in self.learnts.iter_mut() {
+                        learnt.activity /= CLAUSE_RESCALE;
+                    }
+                    self.cla_inc /= CLAUSE_RESCALE;
+                }
+            }
+        }
+        self.cla_inc /= CLAUSE_DECAY;
+        self.reduce_conflicts += 1;
+    }
+
+    //Reduce the learned clauses every this many conflicts (plus
+    //`REDUCE_INC` more each time), or never with None
+    pub fn set_reduce_interval(&mut self, conflicts: Option<usize>) {
+        self.reduce_interval = conflicts;
+        self.reduce_conflicts = 0;
+    }
+
+    //Clauses that are the reason for an assignment we may still need
+    fn locked(&self) -> BitSet {
+        let mut locked: BitSet = self
+            .track
+            .values()
+            .filter_map(|&(_, cause)| cause)
+            .collect();
+        let pending = self.prop_queue.iter().filter_map(|&(_, cause)| cause);
+        locked.extend(pending.chain(self.root_units.iter().map(|&(_, ind)| ind)));
+        locked
+    }
+
+    //Delete the worse half of the learned clauses, by LBD and then activity,
+    //apart from glue clauses and those that are locked
+    fn reduce_db(&mut self) {
+        self.clock.switch(&mut self.stats, Some(Reduce));
+        let locked = self.locked();
+        let mut candidates: Vec<(usize, &Learnt)> = self
+            .learnts
+            .iter()
+            .filter(|&(ind, learnt)| learnt.lbd > GLUE_LBD && !locked.contains(ind))
+            .collect();
+        candidates.sort_by(|a, b| {
+            b.1.lbd
+                .cmp(&a.1.lbd)
+                .then(a.1.activity.total_cmp(&b.1.activity))
+        });
+        let dropped: BitSet = candidates
+            .iter()
+            .take(candidates.len() / 2)
+            .map(|&(ind, _)| ind)
+            .collect();
+        info!("Deleting {} learned clauses", dropped.len());
+        self.stats.deleted += dropped.len() as u64;
+        for ind in dropped.iter() {
+            self.learnts.remove(ind);
+            let cls = std::mem::take(&mut self.clss[ind].cls);
+            if let Some(ref mut proof) = self.proof {
+                proof.delete(&cls);
+            }
+        }
+        for (_, inds) in self.watches.iter_mut() {
+            inds.retain(|&ind| !dropped.contains(ind));
+        }
+        self.reduce_conflicts = 0;
+        self.reduce_interval = self.reduce_interval.map(|n| n + REDUCE_INC);
+    }
+
+    //Literal block distance: the number of levels the lits were set at
+    fn lbd(&self, cls: &[Lit]) -> usize {
+        let lvls: BitSet = cls
+            .iter()
+            .filter_map(|l| self.track.get(l.id().0))
+            .map(|&(DecLevel(lvl), _)| lvl)
+            .collect();
+        lvls.len()
+    }
+
+    //Stop on this handle rather than the solver's own
+    pub(crate) fn set_interrupt(&mut self, interrupt: Interrupt) {
+        self.budget.set_interrupt(interrupt);
+    }
+
+    //Add a clause to the formula, e.g. after a call to solve.
+    //Learned clauses and watches are kept, so the next call carries on
+    //from what has been found so far.
+    pub fn add_clause(&mut self, cls: Clause) {
+        self.backtrack_to_root();
+        if self.branching != Branching::Order {
+            for lit in cls.iter() {
+                if self.interp.get_val(lit).is_none() {
+                    self.order.insert(lit.id().0);
+                }
+            }
+        }
+        let new_ind = self.clss.len();
+        let orig = self.originals;
+        self.originals += 1;
+        //watch lits that aren't already false at the root where possible
+        let (mut open, falsified): (Vec<usize>, Vec<usize>) =
+            (0..cls.len()).partition(|&i| self.interp.get_val(&cls[i]) != Some(false));
+        //an earlier root conflict already has its core
+        let newly_unsat = open.is_empty() && !self.root_conflict;
+        match open.len() {
+            0 => {
+                info!("Added clause is false at the root, UNSAT");
+                self.root_conflict = true;
+                if newly_unsat {
+                    self.log_lemma(&[]);
+                }
+            }
+            1 if self.interp.get_val(&cls[open[0]]).is_none() => {
+                self.root_units.push((cls[open[0]].clone(), new_ind));
+            }
+            _ => {}
+        }
+        open.extend(falsified);
+        if cls.is_empty() {
+            if newly_unsat && self.provenance.is_some() {
+                self.core = Some(vec![orig]);
+            }
+            return;
+        }
+        if let Some(ref mut provenance) = self.provenance {
+            provenance.push(Provenance::Original(orig));
+        }
+        let indices = (open[0], *open.get(1).unwrap_or(&open[0]));
+        add_watched(&mut self.watches, &cls[indices.0], new_ind);
+        if indices.1 != indices.0 {
+            add_watched(&mut self.watches, &cls[indices.1], new_ind);
+        }
+        self.clss.push(WatchedClause { indices, cls });
+        if newly_unsat {
+            let falsified = self.clss[new_ind].cls.clone();
+            self.core = self.find_core(vec![new_ind], &falsified);
+        }
+    }
+
+    //Every model in turn, or with a projection one for each combination of
+    //values of its vars. Each one found is blocked by adding a clause.
+    pub fn models(&mut self, projection: Option<Vec<Id>>) -> Models<'_> {
+        Models::new(self, projection)
+    }
+
+    //Solve with the given lits taken as true.
+    //If that's UNSAT only because of them, the result is `UNSATAssuming`
+    //with the assumptions that were needed to reach the conflict.
+    pub fn solve_with_assumptions(&mut self, assumptions: &[Lit]) -> Satness {
+        self.budget.start(&self.stats);
+        self.progress.start(&self.stats);
+        self.clock.switch(&mut self.stats, Some(Propagate));
+        let result = self.search(assumptions);
+        self.clock.switch(&mut self.stats, None);
+        result
+    }
+
+    fn search(&mut self, assumptions: &[Lit]) -> Satness {
+        self.backtrack_to_root();
+        self.failed = None;
+        if self.root_conflict {
+            return UNSAT("Found conflict".to_string());
+        }
+        self.core = None;
+
+        //handle top level units
+        for (ind, unit) in self.clss.iter().enumerate().filter_map(|(i, c)| {
+            if c.cls.len() == 1 {
+                Some((i, c.cls[0].clone()))
+            } else {
+                None
+            }
+        }) {
+            info!("Found top level unit: {:?}", unit);
+            self.prop_queue.push_back((unit, Some(ind)))
+        }
+        for (unit, ind) in self.root_units.drain(..) {
+            self.prop_queue.push_back((unit, Some(ind)))
+        }
+
+        let mut processing = self.process_queue();
+        for assumption in assumptions {
+            if let Conflict = processing {
+                break;
+            }
+            match self.interp.get_val(assumption) {
+                Some(true) => continue,
+                Some(false) => {
+                    info!("Assumption {:?} is already false", assumption);
+                    let mut failed = self.analyze_final(std::slice::from_ref(assumption));
+                    if !failed.contains(assumption) {
+                        failed.push(assumption.clone());
+                    }
+                    self.core = self.find_core(Vec::new(), std::slice::from_ref(assumption));
+                    return UNSATAssuming(failed);
+                }
+                None => {
+                    self.assumption_lvls = self.trail_lim.len() + 1;
+                    processing = match self.decide_var(Some(assumption.clone())) {
+                        Some(Safe) => self.process_queue(),
+                        _ => Conflict,
+                    };
+                }
             }
         }
+
+        //main loop
+        loop {
+            if let Conflict = processing {
+                return match self.failed.take() {
+                    Some(failed) => UNSATAssuming(failed),
+                    None => {
+                        self.root_conflict = true;
+                        self.log_lemma(&[]);
+                        UNSAT("Found conflict".to_string())
+                    }
+                };
+            }
+
+            if self.progress.due(&self.stats) {
+                self.clock.flush(&mut self.stats);
+                self.progress.report(&self.stats);
+            }
+
+            //only checked here, when nothing is left to propagate, so a
+            //later call can carry on from the same state
+            if let Some(reason) = self.budget.exhausted(&self.stats) {
+                info!("{}", reason);
+                return UNKNOWN(reason);
+            }
+
+            if self
+                .reduce_interval
+                .is_some_and(|n| self.reduce_conflicts >= n)
+            {
+                self.reduce_db();
+            }
+
+            if self.restarts.due() {
+                info!("Restarting");
+                self.backtrack_to(self.assumption_lvls);
+                self.restarts.restarted();
+                self.stats.restarts += 1;
+            }
+
+            processing = match self.decide_var(None) {
+                None => return SAT(self.interp.clone()),
+                Some(Safe) => self.process_queue(),
+                Some(Conflict) => Conflict,
+            };
+        }
     }
 
-    fn handle_conflict(&mut self, conf_i: usize) -> (Clause, DecLevel) {
-        let mut dec_lvl = self.level();
-        let (mut cause, mut back_lvl) = self.trace_conflict(&dec_lvl, &self.clss[conf_i].cls);
-        while back_lvl < dec_lvl {
-            dec_lvl = back_lvl;
-            let trace = self.trace_conflict(&dec_lvl, &cause);
-            //uuugly
-            cause = trace.0;
-            back_lvl = trace.1;
+    //Learn a clause from the conflict and jump back to where it's unit,
+    //unless the conflict doesn't depend on any decisions
+    fn handle_conflict(&mut self, confl: usize) -> Safety {
+        self.stats.conflicts += 1;
+        self.clock.switch(&mut self.stats, Some(Analyze));
+        let confl_lits = self.clss[confl].cls.clone();
+        let confl_lvl = confl_lits.iter().map(|l| self.lvl_of(l)).max().unwrap_or(0);
+        if confl_lvl <= self.assumption_lvls {
+            //we'd have to flip an assumption (or there's nothing left to flip)
+            //so find out which assumptions the conflict depends on
+            info!("Conflict below the assumptions, UNSAT");
+            self.core = self.find_core(vec![confl], &confl_lits);
+            self.failed = if confl_lvl == 0 {
+                None
+            } else {
+                Some(self.analyze_final(&confl_lits))
+            };
+            return Conflict;
+        }
+
+        let mut used = vec![confl];
+        let mut bumped = BitSet::new();
+        let (learned, DecLevel(back_lvl)) = self.analyze(confl, confl_lvl, &mut used, &mut bumped);
+        if self.branching != Branching::Order {
+            for var in bumped.iter() {
+                self.order.bump(var);
+            }
+            self.order.decay();
+        }
+        let lbd = self.lbd(&learned);
+        self.restarts.on_conflict(lbd);
+        let below = self.trail_lim[confl_lvl - 1];
+        self.phases.on_conflict(&self.trail[..below]);
+        self.bump_clauses(&used);
+
+        //the assumptions stay, so a clause unit below them is set just above
+        let assert_lvl = max(back_lvl, self.assumption_lvls);
+        self.backtrack_to(assert_lvl);
+        self.stats.backjumped += (confl_lvl - assert_lvl) as u64;
+        self.stats.learned += 1;
+        self.stats.learned_lits += learned.len() as u64;
+        let uip = learned[0].clone();
+        let new_ind = self.learn_clause(&uip, learned, used);
+        if back_lvl == 0 && self.assumption_lvls > 0 {
+            //it's unit at the root, so it has to be set again after the
+            //assumptions are undone
+            self.root_units.push((uip.clone(), new_ind));
         }
-        (cause, back_lvl)
+        self.prop_queue.push_back((uip, Some(new_ind)));
+        self.clock.switch(&mut self.stats, Some(Propagate));
+        Safe
     }
 
     fn process(&mut self, constr_lit: Lit, cause: Implicant) -> Safety {
+        //it was queued more than once
+        if self.interp.get_val(&constr_lit) == Some(true) {
+            return Safe;
+        }
+        if cause.is_some() {
+            self.stats.propagations += 1;
+        }
         self.set_true(&constr_lit, cause);
         match self.check_watchers(constr_lit) {
             None => Safe,
-            Some(cls_ind) => {
-                //use this to find the back_lvl and the REAL confl cause
-                let (confl_cls, back_lvl) = self.handle_conflict(cls_ind);
-                self.backtrack(confl_cls, back_lvl)
-            }
+            Some(cls_ind) => self.handle_conflict(cls_ind),
         }
     }
 
@@ -340,60 +1015,95 @@ impl SATSolver for Solver {
                 for lit in cls.iter().take(2) {
                     add_watched(&mut watches, lit, ind);
                 }
-                ind = ind + 1;
+                ind += 1;
                 if cls.len() > 1 {
                     WatchedClause {
                         indices: (0, 1),
-                        cls: cls,
+                        cls,
                     }
                 } else {
                     WatchedClause {
                         indices: (0, 0),
-                        cls: cls,
+                        cls,
                     }
                 }
             })
             .collect();
         //debug!("Watched: {:?}",clss);
         //debug!("Watches: {:?}",watches);
-        Solver {
+        let mut solver = Solver {
             interp: interp.unwrap_or_else(|| Interp(VecMap::new())),
-            interp_stack: Vec::new(),
             clss,
             prop_queue: VecDeque::new(),
             track: VecMap::new(),
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
             watches,
-        }
+            assumption_lvls: 0,
+            failed: None,
+            root_conflict: false,
+            root_units: Vec::new(),
+            provenance: None,
+            originals: ind,
+            core: None,
+            learned: 0,
+            proof: None,
+            branching: Branching::default(),
+            order: VarOrder::new(Branching::default()),
+            phases: Phases::new(Phase::default(), 0),
+            restarts: Restarts::new(Restart::default()),
+            learnts: VecMap::new(),
+            cla_inc: 1.0,
+            reduce_interval: Some(REDUCE_FIRST),
+            reduce_conflicts: 0,
+            budget: Budget::new(Limits::default()),
+            stats: Stats::default(),
+            clock: Clock::new(),
+            progress: Progress::new(),
+        };
+        solver.set_branching(Branching::default());
+        solver
     }
 
-    fn solve(&mut self) -> Satness {
-        //handle top level units
-        for unit in self.clss.iter().filter_map(|c| {
-            if c.cls.len() == 1 {
-                Some(c.cls[0].clone())
-            } else {
-                None
-            }
-        }) {
-            info!("Found top level unit: {:?}", unit);
-            self.prop_queue.push_back((unit, None))
+    fn with_config(
+        formula: CNF,
+        interp: Option<Interp>,
+        config: &SolverConfig,
+    ) -> io::Result<Solver> {
+        config
+            .restart
+            .check()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut solver = Solver::create(formula, interp);
+        solver.configure(config);
+        if let Some(ref proof) = config.proof {
+            let file = File::create(&proof.path).map_err(|e| {
+                let msg = format!("couldn't create {}: {}", proof.path.display(), e);
+                io::Error::new(e.kind(), msg)
+            })?;
+            solver.log_proof(BufWriter::new(file), proof.format);
         }
+        Ok(solver)
+    }
 
-        //main loop
-        loop {
-            let processing = match self.process_queue() {
-                Safe => match self.decide_var(None) {
-                    None => return SAT(self.interp.clone()),
-                    Some(safety) => safety,
-                },
-                e => e,
-            };
+    fn set_limits(&mut self, limits: Limits) {
+        self.budget.set_limits(limits);
+    }
 
-            if let Conflict = processing {
-                let reason = format!("Found conflict");
-                return UNSAT(reason);
-            }
-        }
+    fn interrupt_handle(&self) -> Interrupt {
+        self.budget.interrupt_handle()
+    }
+
+    fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    fn on_progress<F: FnMut(&Stats) + Send + 'static>(&mut self, every: Interval, hook: F) {
+        self.progress.set(every, Box::new(hook));
+    }
+
+    fn solve(&mut self) -> Satness {
+        self.solve_with_assumptions(&[])
     }
 }
 
@@ -401,7 +1111,7 @@ impl SATSolver for Solver {
 mod tests {
     use super::super::Lit::{N, P};
     use super::super::{Id, SATSolver};
-    use super::Solver;
+    use super::{DecLevel, Solver};
 
     use env_logger;
 
@@ -439,6 +1149,13 @@ mod tests {
 
         solver.decide_var(Some(P(Id(1))));
         solver.process_queue();
+
+        //4 is the first UIP, and -11 follows from -10 so it's minimized away
+        assert_eq!(solver.clss.last().unwrap().cls, vec![N(Id(4)), P(Id(10))]);
+        //back to where -10 was decided, with -4 set there
+        assert_eq!(solver.level(), DecLevel(3));
+        assert_eq!(solver.interp.get_val(&N(Id(4))), Some(true));
+        assert_eq!(solver.interp.get_val(&P(Id(1))), None);
         /*solver.interp_stack.push(
             (N(Id(9)),
              false,
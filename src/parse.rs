@@ -1,155 +1,411 @@
 use super::{Clause, Id, Lit, CNF};
-use std::fmt::Debug;
-use std::str::CharIndices;
+use log::warn;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::io::BufRead;
 
-#[derive(Debug)]
-pub enum ParseError {
-    Syntax(Box<dyn Debug>),
-    EOF(usize),
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    //a token that isn't a valid literal, e.g. `1a` or `-`
+    BadLiteral,
+    //input ended part way through a clause
+    MissingTerminator,
+    //a char that can't start a literal
+    UnexpectedChar,
+    //a var id that doesn't fit in a usize
+    IdOverflow,
+    //the `p cnf` line couldn't be read
+    Header,
+    //a second `p` line
+    DuplicateHeader,
+    //the underlying reader failed
+    Io(io::ErrorKind),
+    //a literal refers to a var greater than the header declares
+    VarOutOfRange { declared: usize },
+    //the body has a different number of clauses than the header declares
+    ClauseCount { declared: usize, found: usize },
 }
 
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::BadLiteral => write!(f, "invalid literal"),
+            ErrorKind::MissingTerminator => write!(f, "clause is missing its terminating 0"),
+            ErrorKind::UnexpectedChar => write!(f, "unexpected character"),
+            ErrorKind::IdOverflow => write!(f, "variable id is too large"),
+            ErrorKind::Header => write!(f, "expected `p cnf <vars> <clauses>`"),
+            ErrorKind::DuplicateHeader => write!(f, "duplicate `p` line"),
+            ErrorKind::Io(kind) => write!(f, "couldn't read input: {}", kind),
+            ErrorKind::VarOutOfRange { declared } => {
+                write!(
+                    f,
+                    "variable exceeds the {} declared in the header",
+                    declared
+                )
+            }
+            ErrorKind::ClauseCount { declared, found } => write!(
+                f,
+                "header declares {} clauses but the body has {}",
+                declared, found
+            ),
+        }
+    }
+}
+
+//What went wrong, where it starts (1-based) and the text involved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ErrorKind,
+    pub line: usize,
+    pub col: usize,
+    pub token: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if !self.token.is_empty() {
+            write!(f, " `{}`", self.token)?;
+        }
+        write!(f, " at line {}, column {}", self.line, self.col)
+    }
+}
+
+impl Error for ParseError {}
+
 pub type Parse<T> = Result<T, ParseError>;
 
-pub fn parse_file(f: String) -> Parse<CNF> {
-    CNFParser::new(&f).parse_file()
+//Counts declared by a `p cnf <vars> <clauses>` line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub vars: usize,
+    pub clauses: usize,
+}
+
+//How to treat a body that disagrees with its header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    //return an error
+    Strict,
+    //log a warning and keep the formula
+    Lenient,
+}
+
+pub fn parse_file(f: String) -> Parse<(CNF, Option<Header>)> {
+    parse_file_with(f, Mode::Strict)
+}
+
+pub fn parse_file_with(f: String, mode: Mode) -> Parse<(CNF, Option<Header>)> {
+    parse_reader(f.as_bytes(), mode)
+}
+
+pub fn parse_reader<R: BufRead>(input: R, mode: Mode) -> Parse<(CNF, Option<Header>)> {
+    let mut reader = Reader::with_mode(input, mode);
+    let formula = reader.by_ref().collect::<Parse<CNF>>()?;
+    Ok((formula, reader.header()))
 }
 
 pub fn parse_clause(_f: String) -> Parse<Clause> {
     let mut f = _f.clone();
     f.push_str(" 0");
-    CNFParser::new(&f).parse_clause()
+    CNFParser::new(f.as_bytes(), Mode::Strict).parse_clause()
 }
 
 pub fn parse_lit(l: String) -> Parse<Lit> {
-    CNFParser::new(&l).parse_lit()
+    let mut parser = CNFParser::new(l.as_bytes(), Mode::Strict);
+    parser.consume_whitespace();
+    let (line, col) = (parser.line, parser.col);
+    match parser.parse_lit()? {
+        Some(lit) => Ok(lit),
+        None => Err(ParseError {
+            kind: ErrorKind::BadLiteral,
+            line,
+            col,
+            token: "0".to_string(),
+        }),
+    }
 }
 
-struct CNFParser<'a> {
-    curr: char,
-    pos: usize,
-    //buff: String
-    buff: CharIndices<'a>,
+//Reads clauses one at a time from a file, stdin, pipe, ...
+//so the whole input never has to be in memory at once.
+//The body is checked against the header as it goes; any mismatch in the
+//number of clauses is yielded as a final error.
+pub struct Reader<R: BufRead> {
+    parser: CNFParser<R>,
+    done: bool,
 }
 
-impl<'a> CNFParser<'a> {
-    fn new(buff: &'a String) -> CNFParser<'a> {
-        let buff_ = buff.char_indices();
-        CNFParser {
-            curr: ' ',
-            pos: 0,
-            buff: buff_,
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Reader<R> {
+        Reader::with_mode(input, Mode::Strict)
+    }
+
+    pub fn with_mode(input: R, mode: Mode) -> Reader<R> {
+        Reader {
+            parser: CNFParser::new(input, mode),
+            done: false,
         }
     }
 
-    fn parse_file(&mut self) -> Parse<CNF> {
-        let mut formula = Vec::new();
-        loop {
-            match self.parse_line() {
-                Ok(Some(cls)) => formula.push(cls),
-                Ok(None) => return Ok(formula),
-                Err(e) => return Err(e),
+    //The `p cnf` header, once it has been read.
+    //Headers come before the body, so this is known after the first clause.
+    pub fn header(&self) -> Option<Header> {
+        self.parser.header
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Parse<Clause>;
+
+    fn next(&mut self) -> Option<Parse<Clause>> {
+        if self.done {
+            return None;
+        }
+        let next = match self.parser.parse_line() {
+            Ok(None) => self.parser.check_clause_count().err().map(Err),
+            res => res.transpose(),
+        };
+        //an io error cuts the input short, so report it rather than
+        //whatever the parser made of the truncated input
+        let next = match self.parser.io_error.take() {
+            Some(e) => Some(Err(e)),
+            None => next,
+        };
+        if !matches!(next, Some(Ok(_))) {
+            self.done = true;
+        }
+        next
+    }
+}
+
+struct CNFParser<R: BufRead> {
+    curr: Option<char>,
+    line: usize,
+    col: usize,
+    input: R,
+    //the line currently being read and how far into it we are
+    buff: String,
+    buff_pos: usize,
+    io_error: Option<ParseError>,
+    mode: Mode,
+    header: Option<Header>,
+    //largest var seen so far
+    max_var: usize,
+    clauses: usize,
+    //where the first clause beyond the declared count starts
+    extra_clause: Option<(usize, usize)>,
+}
+
+impl<R: BufRead> CNFParser<R> {
+    fn new(input: R, mode: Mode) -> CNFParser<R> {
+        let mut parser = CNFParser {
+            curr: None,
+            line: 1,
+            col: 0,
+            input,
+            buff: String::new(),
+            buff_pos: 0,
+            io_error: None,
+            mode,
+            header: None,
+            max_var: 0,
+            clauses: 0,
+            extra_clause: None,
+        };
+        parser.take();
+        parser
+    }
+
+    fn error_at(&self, kind: ErrorKind, (line, col): (usize, usize), token: String) -> ParseError {
+        ParseError {
+            kind,
+            line,
+            col,
+            token,
+        }
+    }
+
+    //fail in strict mode, only warn in lenient mode
+    fn mismatch(&self, err: ParseError) -> Parse<()> {
+        match self.mode {
+            Mode::Strict => Err(err),
+            Mode::Lenient => {
+                warn!("Body doesn't match header: {}", err);
+                Ok(())
+            }
+        }
+    }
+
+    fn check_clause_count(&self) -> Parse<()> {
+        if let Some(header) = self.header {
+            if self.clauses != header.clauses {
+                let pos = self.extra_clause.unwrap_or((self.line, self.col));
+                let kind = ErrorKind::ClauseCount {
+                    declared: header.clauses,
+                    found: self.clauses,
+                };
+                return self.mismatch(self.error_at(kind, pos, String::new()));
             }
-            self.take()?;
         }
+        Ok(())
     }
 
-    //add more error checking for correct file format
     fn parse_line(&mut self) -> Parse<Option<Clause>> {
-        //move through any whitespace
-        self.consume_whitespace()?;
-        //if we have a comment or %, ignore the line
-        //TODO: take p info into account
-        if self.curr == 'c' || self.curr == 'p' || self.curr == '%' {
-            self.parse_comment().and(self.parse_line())
-        }
-        //we have a 0 at the beginning of the line
-        //indicating nothing left to parse
-        else if self.curr == '0' {
-            Ok(None)
-        } else {
-            self.parse_clause().map(Some)
+        loop {
+            //move through any whitespace
+            self.consume_whitespace();
+            match self.curr {
+                //files needn't end with a 0 line
+                None => return Ok(None),
+                //if we have a comment or %, ignore the line
+                Some('c') | Some('%') => self.parse_comment(),
+                Some('p') => self.parse_header()?,
+                //we have a 0 at the beginning of the line
+                //indicating nothing left to parse
+                Some('0') => return Ok(None),
+                Some(_) => {
+                    let start = (self.line, self.col);
+                    let cls = self.parse_clause()?;
+                    self.clauses += 1;
+                    if let Some(header) = self.header {
+                        if self.clauses > header.clauses && self.extra_clause.is_none() {
+                            self.extra_clause = Some(start);
+                        }
+                    }
+                    return Ok(Some(cls));
+                }
+            }
         }
     }
 
     fn parse_clause(&mut self) -> Parse<Clause> {
         let mut clause: Clause = Vec::new();
-        //move through any whitespace
-        self.consume_whitespace()?;
-        //while we're not at the end of the clause, parse lits
-        while self.curr != '0' {
-            match self.parse_lit() {
-                Ok(lit) => clause.push(lit),
-                Err(e) => return Err(e),
-            }
+        loop {
             //remove whitespace inbetween
-            match self.consume_whitespace() {
-                Err(ParseError::EOF(_)) => break,
-                Err(e) => return Err(e),
-                _ => {}
+            self.consume_whitespace();
+            if self.curr.is_none() {
+                let pos = (self.line, self.col);
+                return Err(self.error_at(ErrorKind::MissingTerminator, pos, String::new()));
+            }
+            //parse lits until we reach the 0 at the end of the clause
+            match self.parse_lit()? {
+                Some(lit) => clause.push(lit),
+                None => return Ok(clause),
             }
         }
-        Ok(clause)
     }
 
-    fn parse_lit(&mut self) -> Parse<Lit> {
-        //move through any whitespace
-        self.consume_whitespace()?;
-        if self.curr == '-' {
-            self.take().and_then(|()| self.parse_ident().map(Lit::N))
-        } else {
-            self.parse_ident().map(Lit::P)
+    //a literal, or None for the terminating 0
+    fn parse_lit(&mut self) -> Parse<Option<Lit>> {
+        let start = (self.line, self.col);
+        let mut token = String::new();
+        while let Some(c) = self.curr.filter(|c| !c.is_whitespace()) {
+            token.push(c);
+            self.take();
+        }
+        let (neg, digits) = match token.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, &token[..]),
+        };
+        if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+            let kind = if token.starts_with(|c: char| c == '-' || c.is_ascii_digit()) {
+                ErrorKind::BadLiteral
+            } else {
+                ErrorKind::UnexpectedChar
+            };
+            return Err(self.error_at(kind, start, token));
+        }
+        if !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(self.error_at(ErrorKind::BadLiteral, start, token));
+        }
+        let id: usize = match digits.parse() {
+            Ok(id) => id,
+            Err(_) => return Err(self.error_at(ErrorKind::IdOverflow, start, token)),
+        };
+        if id == 0 {
+            return if neg {
+                Err(self.error_at(ErrorKind::BadLiteral, start, token))
+            } else {
+                Ok(None)
+            };
         }
+        if let Some(header) = self.header {
+            //only report each new maximum, so lenient mode doesn't flood the log
+            if id > header.vars && id > self.max_var {
+                let kind = ErrorKind::VarOutOfRange {
+                    declared: header.vars,
+                };
+                self.mismatch(self.error_at(kind, start, token))?;
+            }
+        }
+        self.max_var = self.max_var.max(id);
+        Ok(Some(if neg { Lit::N(Id(id)) } else { Lit::P(Id(id)) }))
     }
 
-    fn parse_ident(&mut self) -> Parse<Id> {
-        let mut lit = String::new();
-        while !CNFParser::is_whitespace(self.curr) {
-            lit.push(self.curr);
-            //TODO: make this better with eof maybe self.curr is Option
-            match self.take() {
-                Err(ParseError::EOF(_)) => break,
-                Err(e) => return Err(e),
-                _ => {}
+    fn parse_header(&mut self) -> Parse<()> {
+        let start = (self.line, self.col);
+        let mut line = String::new();
+        while let Some(c) = self.curr.filter(|&c| c != '\n') {
+            line.push(c);
+            self.take();
+        }
+        if self.header.is_some() {
+            return Err(self.error_at(ErrorKind::DuplicateHeader, start, line));
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if let ["p", "cnf", vars, clauses] = fields[..] {
+            if let (Ok(vars), Ok(clauses)) = (vars.parse(), clauses.parse()) {
+                self.header = Some(Header { vars, clauses });
+                return Ok(());
             }
         }
-        lit.parse()
-            .map(Id)
-            .map_err(|e| ParseError::Syntax(Box::new(e)))
-        /*match lit.parse() {
-            Ok(lit)     => Ok(Id(lit)),
-            Err(e)      => Err(ParseError::Syntax(box e))
-        }*/
+        Err(self.error_at(ErrorKind::Header, start, line))
     }
 
-    fn parse_comment(&mut self) -> Parse<()> {
+    fn parse_comment(&mut self) {
         //ignore line
         self.consume_while(&|c| c != '\n')
     }
 
-    fn is_whitespace(c: char) -> bool {
-        c == ' ' || c == '\n'
+    fn consume_whitespace(&mut self) {
+        self.consume_while(&|c| c.is_whitespace())
     }
 
-    fn consume_whitespace(&mut self) -> Parse<()> {
-        self.consume_while(&|c| CNFParser::is_whitespace(c))
+    fn consume_while(&mut self, p: &dyn Fn(char) -> bool) {
+        while self.curr.is_some_and(p) {
+            self.take();
+        }
     }
 
-    fn consume_while(&mut self, p: &dyn Fn(char) -> bool) -> Parse<()> {
-        while p(self.curr) {
-            self.take()?;
+    fn take(&mut self) {
+        //get a char and keep track of where it is
+        if self.curr == Some('\n') {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
         }
-        Ok(())
+        self.curr = self.next_char();
     }
 
-    fn take(&mut self) -> Parse<()> {
-        //get a char, wrap any errors and update self.token
-        match self.buff.next() {
-            None => Err(ParseError::EOF(self.pos)),
-            Some((pos, ch)) => {
-                self.pos = pos;
-                self.curr = ch;
-                Ok(())
+    fn next_char(&mut self) -> Option<char> {
+        if self.buff_pos >= self.buff.len() {
+            self.buff.clear();
+            self.buff_pos = 0;
+            match self.input.read_line(&mut self.buff) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => {
+                    let pos = (self.line, self.col);
+                    self.io_error =
+                        Some(self.error_at(ErrorKind::Io(e.kind()), pos, e.to_string()));
+                    return None;
+                }
             }
         }
+        let c = self.buff[self.buff_pos..].chars().next()?;
+        self.buff_pos += c.len_utf8();
+        Some(c)
     }
 }
@@ -0,0 +1,90 @@
+use super::write::write_clause;
+use super::{Id, Lit};
+use serde::Deserialize;
+use std::io;
+use std::io::Write;
+
+//How the lines of a DRAT proof are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    //`1 -2 0` to add a clause, `d 1 -2 0` to delete one
+    #[default]
+    Text,
+    //the compressed format drat-trim reads with `-i`
+    Binary,
+}
+
+//A DRAT proof that clauses are written to as they are learned or deleted.
+//The first error is kept and no more is written after it, so a solver
+//doesn't have to check after every clause, only at the end with `finish`.
+pub struct Writer<W: Write> {
+    out: W,
+    format: Format,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(out: W, format: Format) -> Writer<W> {
+        Writer {
+            out,
+            format,
+            error: None,
+        }
+    }
+
+    pub fn add(&mut self, cls: &[Lit]) {
+        self.step(b'a', cls)
+    }
+
+    pub fn delete(&mut self, cls: &[Lit]) {
+        self.step(b'd', cls)
+    }
+
+    //Flush the proof, giving back the writer or the first error hit
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn step(&mut self, kind: u8, cls: &[Lit]) {
+        if self.error.is_some() {
+            return;
+        }
+        let res = match self.format {
+            Format::Text => {
+                if kind == b'd' {
+                    write!(self.out, "d ").and_then(|_| write_clause(&mut self.out, cls))
+                } else {
+                    write_clause(&mut self.out, cls)
+                }
+            }
+            Format::Binary => write_binary(&mut self.out, kind, cls),
+        };
+        if let Err(e) = res {
+            self.error = Some(e);
+        }
+    }
+}
+
+//Each lit is mapped to 2 * var (+ 1 if negative) and written 7 bits at a
+//time, least significant first, with the high bit set on all but the last byte
+fn write_binary<W: Write>(out: &mut W, kind: u8, cls: &[Lit]) -> io::Result<()> {
+    let mut bytes = vec![kind];
+    for lit in cls {
+        let mut n = match *lit {
+            Lit::P(Id(id)) => 2 * id,
+            Lit::N(Id(id)) => 2 * id + 1,
+        };
+        while n > 0x7f {
+            bytes.push((n & 0x7f) as u8 | 0x80);
+            n >>= 7;
+        }
+        bytes.push(n as u8);
+    }
+    bytes.push(0);
+    out.write_all(&bytes)
+}
@@ -0,0 +1,152 @@
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+//When the CDCL solver gives up on its decisions and starts again from the
+//root, keeping what it has learned
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Restart {
+    Never,
+    //every this many conflicts
+    Fixed(usize),
+    //after this many conflicts times the next term of the Luby sequence,
+    //1, 1, 2, 1, 1, 2, 4, ...
+    Luby(usize),
+    //after `first` conflicts, then `factor` times as many as the last time
+    Geometric { first: usize, factor: f64 },
+    //glucose style: when the clauses learned recently are worse (have a
+    //higher LBD) than those learned so far
+    Glucose,
+}
+
+impl Default for Restart {
+    fn default() -> Self {
+        Restart::Luby(100)
+    }
+}
+
+impl Restart {
+    //Err for a policy that would restart before every decision, so the
+    //search never got anywhere
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            Restart::Fixed(0) | Restart::Luby(0) | Restart::Geometric { first: 0, .. } => {
+                Err("restarts need at least 1 conflict between them".to_string())
+            }
+            Restart::Geometric { factor, .. } if factor < 1.0 || factor.is_nan() => Err(format!(
+                "the geometric restart factor must be at least 1, not {}",
+                factor
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+//How many learned clauses glucose looks back over
+const GLUCOSE_WINDOW: usize = 50;
+//How much worse they have to be than the average
+const GLUCOSE_MARGIN: f64 = 0.8;
+
+//The i-th term (from 0) of the Luby sequence
+fn luby(mut i: usize) -> usize {
+    //find the finite subsequence that holds i, and its size
+    let (mut size, mut seq) = (1, 0);
+    while size < i + 1 {
+        seq += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) >> 1;
+        seq -= 1;
+        i %= size;
+    }
+    1 << seq
+}
+
+//Counts conflicts to tell when the policy says to restart
+pub(crate) struct Restarts {
+    policy: Restart,
+    restarts: usize,
+    conflicts: usize,
+    //the LBDs of the last clauses learned
+    recent: VecDeque<usize>,
+    lbd_sum: usize,
+    learned: usize,
+}
+
+impl Restarts {
+    pub(crate) fn new(policy: Restart) -> Restarts {
+        Restarts {
+            policy,
+            restarts: 0,
+            conflicts: 0,
+            recent: VecDeque::new(),
+            lbd_sum: 0,
+            learned: 0,
+        }
+    }
+
+    pub(crate) fn on_conflict(&mut self, lbd: usize) {
+        self.conflicts += 1;
+        self.lbd_sum += lbd;
+        self.learned += 1;
+        if self.recent.len() == GLUCOSE_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(lbd);
+    }
+
+    pub(crate) fn due(&self) -> bool {
+        match self.policy {
+            Restart::Never => false,
+            Restart::Fixed(n) => self.conflicts >= n,
+            Restart::Luby(unit) => self.conflicts >= unit * luby(self.restarts),
+            Restart::Geometric { first, factor } => {
+                self.conflicts as f64 >= first as f64 * factor.powi(self.restarts as i32)
+            }
+            Restart::Glucose => {
+                if self.recent.len() < GLUCOSE_WINDOW {
+                    return false;
+                }
+                let recent = self.recent.iter().sum::<usize>() as f64 / GLUCOSE_WINDOW as f64;
+                let overall = self.lbd_sum as f64 / self.learned as f64;
+                recent * GLUCOSE_MARGIN > overall
+            }
+        }
+    }
+
+    pub(crate) fn restarted(&mut self) {
+        self.restarts += 1;
+        self.conflicts = 0;
+        self.recent.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{luby, Restart, Restarts};
+
+    #[test]
+    fn test_luby() {
+        let seq: Vec<usize> = (0..15).map(luby).collect();
+        assert_eq!(seq, vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn test_geometric() {
+        let mut restarts = Restarts::new(Restart::Geometric {
+            first: 10,
+            factor: 1.5,
+        });
+        let mut at = Vec::new();
+        for conflict in 1..=40 {
+            restarts.on_conflict(2);
+            if restarts.due() {
+                at.push(conflict);
+                restarts.restarted();
+            }
+        }
+        //after 10, then 15, then 22.5 more
+        assert_eq!(at, vec![10, 25]);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::budget::CLOCK_PERIOD;
+use std::time::{Duration, Instant};
+
+//What a solver has done over all its calls to solve, from `SATSolver::stats`.
+//Each solver counts what it has, e.g. only nonchro learns clauses or restarts.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Stats {
+    pub decisions: u64,
+    //lits set by unit propagation
+    pub propagations: u64,
+    pub conflicts: u64,
+    pub learned: u64,
+    //learned clauses deleted when reducing
+    pub deleted: u64,
+    //lits in all the learned clauses, for their average length
+    pub learned_lits: u64,
+    //decision levels undone by all the conflicts, for the average backjump
+    pub backjumped: u64,
+    pub restarts: u64,
+    pub max_level: usize,
+    //picking the next var to decide
+    pub decide_time: Duration,
+    pub propagate_time: Duration,
+    //analyzing conflicts and backtracking
+    pub analyze_time: Duration,
+    //reducing the learned clauses
+    pub reduce_time: Duration,
+}
+
+impl Stats {
+    pub fn avg_learned_len(&self) -> f64 {
+        ratio(self.learned_lits, self.learned)
+    }
+
+    pub fn avg_backjump(&self) -> f64 {
+        ratio(self.backjumped, self.conflicts)
+    }
+
+    //Add what another solver has done, for a search that uses several
+    pub(crate) fn add(&mut self, other: &Stats) {
+        self.decisions += other.decisions;
+        self.propagations += other.propagations;
+        self.conflicts += other.conflicts;
+        self.learned += other.learned;
+        self.deleted += other.deleted;
+        self.learned_lits += other.learned_lits;
+        self.backjumped += other.backjumped;
+        self.restarts += other.restarts;
+        self.max_level = self.max_level.max(other.max_level);
+        self.decide_time += other.decide_time;
+        self.propagate_time += other.propagate_time;
+        self.analyze_time += other.analyze_time;
+        self.reduce_time += other.reduce_time;
+    }
+}
+
+fn ratio(n: u64, d: u64) -> f64 {
+    if d == 0 {
+        0.0
+    } else {
+        n as f64 / d as f64
+    }
+}
+
+//How often a progress hook is called
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interval {
+    Conflicts(u64),
+    Time(Duration),
+}
+
+type Hook = Box<dyn FnMut(&Stats) + Send>;
+
+//Calls the hook set by `SATSolver::on_progress`, when it's due
+pub(crate) struct Progress {
+    hook: Option<(Interval, Hook)>,
+    //when the hook was last called, or the solve started
+    conflicts: u64,
+    since: Instant,
+    checks: u64,
+}
+
+impl Progress {
+    pub(crate) fn new() -> Progress {
+        Progress {
+            hook: None,
+            conflicts: 0,
+            since: Instant::now(),
+            checks: 0,
+        }
+    }
+
+    pub(crate) fn set(&mut self, every: Interval, hook: Hook) {
+        self.hook = Some((every, hook));
+    }
+
+    pub(crate) fn start(&mut self, stats: &Stats) {
+        self.conflicts = stats.conflicts;
+        self.since = Instant::now();
+        self.checks = 0;
+    }
+
+    pub(crate) fn due(&mut self, stats: &Stats) -> bool {
+        match self.hook {
+            None => false,
+            Some((Interval::Conflicts(n), _)) => stats.conflicts >= self.conflicts + n,
+            Some((Interval::Time(t), _)) => {
+                self.checks += 1;
+                self.checks.is_multiple_of(CLOCK_PERIOD) && self.since.elapsed() >= t
+            }
+        }
+    }
+
+    pub(crate) fn report(&mut self, stats: &Stats) {
+        if let Some((_, ref mut hook)) = self.hook {
+            hook(stats);
+        }
+        self.conflicts = stats.conflicts;
+        self.since = Instant::now();
+    }
+}
+
+//The part of the search that time is charged to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stage {
+    Decide,
+    Propagate,
+    Analyze,
+    Reduce,
+}
+
+//Times the stages by reading the clock only when the search moves from one
+//to the next
+pub(crate) struct Clock {
+    stage: Option<Stage>,
+    since: Instant,
+}
+
+impl Clock {
+    pub(crate) fn new() -> Clock {
+        Clock {
+            stage: None,
+            since: Instant::now(),
+        }
+    }
+
+    //Charge the time since the last switch to the stage we were in, None
+    //when the search stops
+    pub(crate) fn switch(&mut self, stats: &mut Stats, stage: Option<Stage>) {
+        let now = Instant::now();
+        let spent = now - self.since;
+        match self.stage {
+            Some(Stage::Decide) => stats.decide_time += spent,
+            Some(Stage::Propagate) => stats.propagate_time += spent,
+            Some(Stage::Analyze) => stats.analyze_time += spent,
+            Some(Stage::Reduce) => stats.reduce_time += spent,
+            None => {}
+        }
+        self.stage = stage;
+        self.since = now;
+    }
+
+    //Charge the time so far without leaving the stage
+    pub(crate) fn flush(&mut self, stats: &mut Stats) {
+        self.switch(stats, self.stage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, Stage, Stats};
+    use std::time::Duration;
+
+    #[test]
+    fn test_clock() {
+        let mut stats = Stats::default();
+        let mut clock = Clock::new();
+        clock.switch(&mut stats, Some(Stage::Analyze));
+        std::thread::sleep(Duration::from_millis(5));
+        clock.switch(&mut stats, None);
+        std::thread::sleep(Duration::from_millis(5));
+        clock.switch(&mut stats, Some(Stage::Decide));
+        assert!(stats.analyze_time >= Duration::from_millis(5));
+        //nothing is charged while stopped
+        assert_eq!(stats.decide_time, Duration::ZERO);
+        assert_eq!(stats.propagate_time, Duration::ZERO);
+        assert_eq!(stats.avg_backjump(), 0.0);
+    }
+}
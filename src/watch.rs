@@ -1,6 +1,10 @@
 use self::Safety::{Conflict, Safe};
+use super::budget::{Budget, Interrupt};
+use super::config::Limits;
+use super::stats::Stage::{Analyze, Decide, Propagate};
+use super::stats::{Clock, Interval, Progress, Stats};
 use super::Satness;
-use super::Satness::{SAT, UNSAT};
+use super::Satness::{SAT, UNKNOWN, UNSAT};
 use super::{Clause, Interp, Lit, SATSolver, CNF};
 use log::info;
 use std::collections::VecDeque;
@@ -9,7 +13,7 @@ use vec_map::VecMap;
 
 //Watched clauses
 #[derive(Debug)]
-struct WatchedClause {
+pub(crate) struct WatchedClause {
     indices: (usize, usize),
     cls: Clause,
 }
@@ -25,6 +29,16 @@ pub enum PropRes {
 }
 
 impl WatchedClause {
+    //Watch the first two lits, or the only one of a unit clause
+    pub(crate) fn new(cls: Clause) -> WatchedClause {
+        let indices = if cls.len() > 1 { (0, 1) } else { (0, 0) };
+        WatchedClause { indices, cls }
+    }
+
+    pub(crate) fn lits(&self) -> &[Lit] {
+        &self.cls
+    }
+
     fn get_watch_info(&self, lit: &Lit) -> FstOrSnd {
         FstOrSnd(self.cls[self.indices.0] != lit.not())
     }
@@ -49,7 +63,7 @@ impl WatchedClause {
         }
     }
 
-    fn bcp(&mut self, interp: &Interp, lit: &Lit) -> PropRes {
+    pub(crate) fn bcp(&mut self, interp: &Interp, lit: &Lit) -> PropRes {
         let watch_info = self.get_watch_info(lit);
         let other_lit = self.get_other_watched(&watch_info).clone();
         let other_val = interp.get_val(&other_lit);
@@ -82,7 +96,7 @@ impl WatchedClause {
     }
 }
 
-fn add_watched(watches: &mut VecMap<Vec<usize>>, lit: &Lit, ind: usize) {
+pub(crate) fn add_watched(watches: &mut VecMap<Vec<usize>>, lit: &Lit, ind: usize) {
     let id = lit.not().as_usize();
     match watches.entry(id) {
         Vacant(entry) => {
@@ -96,13 +110,17 @@ type WatcherList = VecMap<Vec<usize>>;
 type WatchedFormula = Vec<WatchedClause>;
 
 pub struct Solver {
-    //Interpretation stack
-    //the var we set to true, whether this was after a conflict and the interp
-    interp_stack: Vec<(Lit, bool, Interp)>,
+    //Decision stack
+    //the var we set to true, whether this was after a conflict and where
+    //it is on the trail
+    decisions: Vec<(Lit, bool, usize)>,
 
     //Current interpretation
     interp: Interp,
 
+    //lits in the order they were set, so backtracking only unsets the tail
+    trail: Vec<Lit>,
+
     //Clauses and constraints
     clss: WatchedFormula,
 
@@ -111,6 +129,14 @@ pub struct Solver {
 
     //Watch list
     watches: WatcherList,
+
+    budget: Budget,
+
+    stats: Stats,
+
+    clock: Clock,
+
+    progress: Progress,
 }
 
 enum Safety {
@@ -161,23 +187,27 @@ impl Solver {
         //just reverse the most recent non post_conflicted assignment
         //reversing also all the propagated stuff
         //for one level change nothing
+        let level = self.decisions.len();
         loop {
             info!("Backtrack up a level");
-            match self.interp_stack.pop() {
-                Some((last, post_confl, a)) => {
+            match self.decisions.pop() {
+                Some((last, post_confl, start)) => {
                     info!("Unsetting {:?}", last);
+                    for lit in self.trail.drain(start..) {
+                        self.interp.unset(&lit);
+                    }
                     if !post_confl {
-                        self.interp = a;
                         info!("Trying {:?}, set: {:?}  -> true", last.not(), last.id());
-                        self.interp_stack
-                            .push((last.clone(), true, self.interp.clone()));
+                        self.decisions.push((last.clone(), true, start));
+                        self.stats.backjumped += (level - self.decisions.len()) as u64;
                         self.prop_queue.clear();
-                        self.process(last.not());
-                        return Safe;
+                        self.clock.switch(&mut self.stats, Some(Propagate));
+                        return self.process(last.not());
                     }
                 }
                 None => {
                     info!("Hit root level, UNSAT");
+                    self.stats.backjumped += level as u64;
                     return Conflict;
                 }
             }
@@ -185,6 +215,7 @@ impl Solver {
     }
 
     fn decide_var(&mut self, lit: Option<Lit>) -> Option<Safety> {
+        self.clock.switch(&mut self.stats, Some(Decide));
         lit.or_else(|| self.find_var()).map(|decision| {
             // here we need to pick a new var
             // because we know nothing more is constrainted
@@ -195,22 +226,33 @@ impl Solver {
                 decision.id(),
                 decision.eval(true)
             );
-            self.interp_stack
-                .push((decision.clone(), false, self.interp.clone()));
+            self.stats.decisions += 1;
+            self.decisions
+                .push((decision.clone(), false, self.trail.len()));
+            self.stats.max_level = self.stats.max_level.max(self.decisions.len());
+            self.clock.switch(&mut self.stats, Some(Propagate));
             self.process(decision)
         })
     }
 
     fn process(&mut self, constr_lit: Lit) -> Safety {
+        if self.interp.get_val(&constr_lit).is_none() {
+            self.trail.push(constr_lit.clone());
+        }
         self.interp.set_true(&constr_lit);
         match self.check_watchers(constr_lit) {
             None => Safe,
-            Some(_) => self.backtrack(),
+            Some(_) => {
+                self.stats.conflicts += 1;
+                self.clock.switch(&mut self.stats, Some(Analyze));
+                self.backtrack()
+            }
         }
     }
 
     fn process_queue(&mut self) -> Safety {
         while let Some(constr_lit) = self.prop_queue.pop_front() {
+            self.stats.propagations += 1;
             let process = self.process(constr_lit);
             if let Conflict = process {
                 return Conflict;
@@ -218,46 +260,8 @@ impl Solver {
         }
         Safe
     }
-}
-
-impl SATSolver for Solver {
-    fn create(formula: CNF, interp: Option<Interp>) -> Solver {
-        //info!("{:?}", formula);
-        //let mut watches = HashMap::<Lit, Vec<usize>>::new();
-        let mut watches = VecMap::new();
-        let mut ind: usize = 0;
-        let clss = formula
-            .into_iter()
-            .map(|cls| {
-                for lit in cls.iter().take(2) {
-                    add_watched(&mut watches, lit, ind);
-                }
-                ind = ind + 1;
-                if cls.len() > 1 {
-                    WatchedClause {
-                        indices: (0, 1),
-                        cls: cls,
-                    }
-                } else {
-                    WatchedClause {
-                        indices: (0, 0),
-                        cls: cls,
-                    }
-                }
-            })
-            .collect();
-        //debug!("Watched: {:?}",clss);
-        //debug!("Watches: {:?}",watches);
-        Solver {
-            interp: interp.unwrap_or_else(|| Interp(VecMap::new())),
-            interp_stack: Vec::new(),
-            clss: clss,
-            prop_queue: VecDeque::new(),
-            watches: watches,
-        }
-    }
 
-    fn solve(&mut self) -> Satness {
+    fn search(&mut self) -> Satness {
         //handle top level units
         for unit in self.clss.iter().filter_map(|c| {
             if c.cls.len() == 1 {
@@ -272,6 +276,13 @@ impl SATSolver for Solver {
 
         //main loop
         loop {
+            if self.progress.due(&self.stats) {
+                self.clock.flush(&mut self.stats);
+                self.progress.report(&self.stats);
+            }
+            if let Some(reason) = self.budget.exhausted(&self.stats) {
+                return UNKNOWN(reason);
+            }
             let processing = match self.process_queue() {
                 Safe => match self.decide_var(None) {
                     None => return SAT(self.interp.clone()),
@@ -281,9 +292,67 @@ impl SATSolver for Solver {
             };
 
             if let Conflict = processing {
-                let reason = format!("Found conflict");
+                let reason = "Found conflict".to_string();
                 return UNSAT(reason);
             }
         }
     }
 }
+
+impl SATSolver for Solver {
+    fn create(formula: CNF, interp: Option<Interp>) -> Solver {
+        //info!("{:?}", formula);
+        //let mut watches = HashMap::<Lit, Vec<usize>>::new();
+        let mut watches = VecMap::new();
+        let mut ind: usize = 0;
+        let clss = formula
+            .into_iter()
+            .map(|cls| {
+                for lit in cls.iter().take(2) {
+                    add_watched(&mut watches, lit, ind);
+                }
+                ind += 1;
+                WatchedClause::new(cls)
+            })
+            .collect();
+        //debug!("Watched: {:?}",clss);
+        //debug!("Watches: {:?}",watches);
+        Solver {
+            interp: interp.unwrap_or_else(|| Interp(VecMap::new())),
+            decisions: Vec::new(),
+            trail: Vec::new(),
+            clss,
+            prop_queue: VecDeque::new(),
+            watches,
+            budget: Budget::new(Limits::default()),
+            stats: Stats::default(),
+            clock: Clock::new(),
+            progress: Progress::new(),
+        }
+    }
+
+    fn set_limits(&mut self, limits: Limits) {
+        self.budget.set_limits(limits);
+    }
+
+    fn interrupt_handle(&self) -> Interrupt {
+        self.budget.interrupt_handle()
+    }
+
+    fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    fn on_progress<F: FnMut(&Stats) + Send + 'static>(&mut self, every: Interval, hook: F) {
+        self.progress.set(every, Box::new(hook));
+    }
+
+    fn solve(&mut self) -> Satness {
+        self.budget.start(&self.stats);
+        self.progress.start(&self.stats);
+        self.clock.switch(&mut self.stats, Some(Propagate));
+        let result = self.search();
+        self.clock.switch(&mut self.stats, None);
+        result
+    }
+}
@@ -0,0 +1,60 @@
+use super::parse::Header;
+use super::{Lit, CNF};
+use std::io;
+use std::io::Write;
+
+//The header that describes a formula: its largest var and number of clauses
+pub fn header_of(formula: &CNF) -> Header {
+    let vars = formula
+        .iter()
+        .flat_map(|c| c.iter())
+        .map(|l| l.id().0)
+        .max()
+        .unwrap_or(0);
+    Header {
+        vars,
+        clauses: formula.len(),
+    }
+}
+
+//Write the formula in DIMACS CNF, preceded by the comments (one `c` line
+//per line of each comment) and a `p cnf` line so it can be read back with
+//`parse::parse_file`.
+//DIMACS has no way to write an empty clause that reads back the same, so
+//a formula with one is an `InvalidInput` error, before anything's written.
+pub fn write_file<W: Write>(out: &mut W, formula: &CNF, comments: &[&str]) -> io::Result<()> {
+    if formula.iter().any(|cls| cls.is_empty()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "an empty clause can't be written in DIMACS",
+        ));
+    }
+    for line in comments.iter().flat_map(|c| c.lines()) {
+        if line.is_empty() {
+            writeln!(out, "c")?;
+        } else {
+            writeln!(out, "c {}", line)?;
+        }
+    }
+    let Header { vars, clauses } = header_of(formula);
+    writeln!(out, "p cnf {} {}", vars, clauses)?;
+    for cls in formula.iter() {
+        write_clause(out, cls)?;
+    }
+    Ok(())
+}
+
+//Write a single clause on its own line, terminated by 0.
+//An empty clause comes out as a lone 0, as DRAT proofs want, but `parse`
+//takes that as the SATLIB end of formula marker.
+pub fn write_clause<W: Write>(out: &mut W, cls: &[Lit]) -> io::Result<()> {
+    for lit in cls.iter() {
+        write_lit(out, lit)?;
+        write!(out, " ")?;
+    }
+    writeln!(out, "0")
+}
+
+pub fn write_lit<W: Write>(out: &mut W, lit: &Lit) -> io::Result<()> {
+    write!(out, "{}", lit)
+}
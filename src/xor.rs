@@ -0,0 +1,69 @@
+use super::Lit::{N, P};
+use super::{Clause, Id, CNF};
+
+//Longest XOR encoded directly, as 2^(len-1) clauses. Longer ones are cut
+//into pieces this long, chained together by fresh vars.
+const CUT: usize = 4;
+
+//The vars XOR together to the parity, i.e. an odd number of them are true
+//if it's true
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xor {
+    pub vars: Vec<Id>,
+    pub parity: bool,
+}
+
+impl Xor {
+    pub fn new(vars: Vec<Id>, parity: bool) -> Xor {
+        Xor { vars, parity }
+    }
+
+    //Clauses that hold iff the XOR does, over its vars and fresh ones
+    //numbered from `next_var`, which is moved past them.
+    //An empty XOR of parity true is the empty clause.
+    pub fn to_cnf(&self, next_var: &mut usize) -> CNF {
+        let mut vars = self.vars.clone();
+        let mut cnf = Vec::new();
+        //a var twice cancels out
+        vars.sort_by_key(|&Id(id)| id);
+        let mut kept: Vec<Id> = Vec::new();
+        for var in vars {
+            if kept.last() == Some(&var) {
+                kept.pop();
+            } else {
+                kept.push(var);
+            }
+        }
+        let mut vars = kept;
+        while vars.len() > CUT {
+            //the fresh var is the XOR of the first few, and takes their place
+            let fresh = Id(*next_var);
+            *next_var += 1;
+            let mut piece: Vec<Id> = vars.drain(..CUT - 1).collect();
+            piece.push(fresh.clone());
+            cnf.extend(direct(&piece, false));
+            vars.push(fresh);
+        }
+        cnf.extend(direct(&vars, self.parity));
+        cnf
+    }
+}
+
+//A clause against every assignment of the vars with the wrong parity
+fn direct(vars: &[Id], parity: bool) -> CNF {
+    (0..1u32 << vars.len())
+        .filter(|bits| (bits.count_ones() % 2 == 1) != parity)
+        .map(|bits| -> Clause {
+            vars.iter()
+                .enumerate()
+                .map(|(i, var)| {
+                    if bits & 1 << i != 0 {
+                        N(var.clone())
+                    } else {
+                        P(var.clone())
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,92 @@
+use num_bigint::BigUint;
+use sat::approx::ApproxCounter;
+use sat::config::Limits;
+use sat::count::Counter;
+use sat::xor::Xor;
+use sat::Lit::{N, P};
+use sat::{Id, CNF};
+
+//x1 or x2, x3 or x4, ..., 3^pairs models
+fn pairs(pairs: usize) -> CNF {
+    (0..pairs)
+        .map(|i| vec![P(Id(2 * i + 1)), P(Id(2 * i + 2))])
+        .collect()
+}
+
+#[test]
+fn xor_clauses() {
+    for len in 0..9 {
+        for &parity in &[false, true] {
+            let vars: Vec<Id> = (1..=len).map(Id).collect();
+            let mut next_var = len + 1;
+            let cnf = Xor::new(vars.clone(), parity).to_cnf(&mut next_var);
+            //every assignment of the vars, each with one value of the fresh ones
+            for bits in 0..1u32 << len {
+                let mut fixed = cnf.clone();
+                for id in 1..=len {
+                    let lit = if bits & 1 << (id - 1) != 0 {
+                        P(Id(id))
+                    } else {
+                        N(Id(id))
+                    };
+                    fixed.push(vec![lit]);
+                }
+                let expected = (bits.count_ones() % 2 == 1) == parity;
+                let count = Counter::new(fixed, next_var - 1).count().unwrap();
+                assert_eq!(count, BigUint::from(expected as u32), "{} {}", len, bits);
+            }
+        }
+    }
+    //a var twice cancels out
+    let mut next_var = 3;
+    let cnf = Xor::new(vec![Id(1), Id(2), Id(1)], true).to_cnf(&mut next_var);
+    assert_eq!(cnf, vec![vec![P(Id(2))]]);
+}
+
+#[test]
+fn small_counts_exact() {
+    let mut counter = ApproxCounter::new(pairs(3), 7);
+    assert_eq!(counter.count(), Ok(BigUint::from(54u32)));
+
+    let mut counter = ApproxCounter::new(vec![vec![P(Id(1))], vec![N(Id(1))]], 1);
+    assert_eq!(counter.count(), Ok(BigUint::ZERO));
+
+    //9 values of 1 to 4, 3 of 19 and 20, and 21 is in no clause
+    let mut counter = ApproxCounter::new(pairs(10), 20);
+    let projection: Vec<Id> = (1..=4).chain(19..=21).map(Id).collect();
+    counter.set_projection(&projection);
+    assert_eq!(counter.count(), Ok(BigUint::from(9u32 * 3 * 2)));
+}
+
+#[test]
+fn estimate_within_tolerance() {
+    let exact = 3f64.powi(6);
+    let estimate = |seed| {
+        let mut counter = ApproxCounter::new(pairs(6), 12);
+        counter.set_tolerance(0.8, 0.5);
+        counter.set_seed(seed);
+        counter.count().unwrap()
+    };
+    let first = estimate(7);
+    let approx: f64 = first.to_string().parse().unwrap();
+    assert!(approx >= exact / 1.8 && approx <= exact * 1.8, "{}", approx);
+    //the same seed gives the same hashes
+    assert_eq!(estimate(7), first);
+}
+
+#[test]
+fn stopped_counts() {
+    let mut counter = ApproxCounter::new(pairs(9), 18);
+    counter.set_limits(Limits {
+        decisions: Some(0),
+        ..Limits::default()
+    });
+    assert_eq!(
+        counter.count(),
+        Err("Decision limit of 0 reached".to_string())
+    );
+
+    let mut counter = ApproxCounter::new(pairs(9), 18);
+    counter.interrupt_handle().interrupt();
+    assert_eq!(counter.count(), Err("Interrupted".to_string()));
+}
@@ -0,0 +1,42 @@
+use proptest::prelude::*;
+use sat::Lit::{N, P};
+use sat::{check, Id, Interp, Lit, CNF};
+use std::collections::HashSet;
+
+//The values of these vars in the model
+pub fn values(model: &Interp, vars: &[usize]) -> Vec<Option<bool>> {
+    vars.iter().map(|&id| model.get_val(&P(Id(id)))).collect()
+}
+
+#[allow(dead_code)]
+pub fn vars_of(cnf: &CNF) -> Vec<usize> {
+    let mut vars: Vec<usize> = cnf.iter().flatten().map(|l| l.id().0).collect();
+    vars.sort_unstable();
+    vars.dedup();
+    vars
+}
+
+//Every assignment to the vars that satisfies the formula, projected
+pub fn brute_force(cnf: &CNF, vars: &[usize], projection: &[usize]) -> HashSet<Vec<Option<bool>>> {
+    let mut found = HashSet::new();
+    for bits in 0..1u32 << vars.len() {
+        let mut interp = Interp::new();
+        for (i, &id) in vars.iter().enumerate() {
+            let lit = if bits & 1 << i != 0 {
+                P(Id(id))
+            } else {
+                N(Id(id))
+            };
+            interp.set_true(&lit);
+        }
+        if check(cnf, &interp) {
+            found.insert(values(&interp, projection));
+        }
+    }
+    found
+}
+
+//A lit of one of the vars 1 to 8
+pub fn lit() -> impl Strategy<Value = Lit> {
+    (1..9usize, any::<bool>()).prop_map(|(id, pos)| if pos { P(Id(id)) } else { N(Id(id)) })
+}
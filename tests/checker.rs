@@ -0,0 +1,97 @@
+use sat::checker::{self, CheckError, DratStep, ErrorKind, LratStep};
+use sat::Lit::{N, P};
+use sat::{proof, Id, CNF};
+
+//(1 | 2), (-1 | 2), (1 | -2), (-1 | -2)
+fn all_pairs() -> CNF {
+    vec![
+        vec![P(Id(1)), P(Id(2))],
+        vec![N(Id(1)), P(Id(2))],
+        vec![P(Id(1)), N(Id(2))],
+        vec![N(Id(1)), N(Id(2))],
+    ]
+}
+
+#[test]
+fn check_drat() {
+    let text = "c learn 2, then it's a conflict\n2 0\nd -1 2 0\n0\n";
+    let steps = checker::read_drat(text.as_bytes(), proof::Format::Text).unwrap();
+    assert_eq!(
+        steps,
+        vec![
+            DratStep::Add(vec![P(Id(2))]),
+            DratStep::Delete(vec![N(Id(1)), P(Id(2))]),
+            DratStep::Add(vec![]),
+        ]
+    );
+    assert_eq!(checker::check_drat(&all_pairs(), &steps), Ok(()));
+}
+
+#[test]
+fn check_binary_drat() {
+    let mut writer = proof::Writer::new(Vec::new(), proof::Format::Binary);
+    writer.add(&[P(Id(2))]);
+    writer.delete(&[N(Id(1)), P(Id(2))]);
+    writer.add(&[]);
+    let written = writer.finish().unwrap();
+    let steps = checker::read_drat(&written[..], proof::Format::Binary).unwrap();
+    assert_eq!(steps.len(), 3);
+    assert_eq!(checker::check_drat(&all_pairs(), &steps), Ok(()));
+}
+
+#[test]
+fn reject_drat() {
+    //1 doesn't follow from the first three clauses alone
+    let cnf = all_pairs()[..3].to_vec();
+    let steps = vec![DratStep::Add(vec![N(Id(1))]), DratStep::Add(vec![])];
+    assert_eq!(
+        checker::check_drat(&cnf, &steps),
+        Err(CheckError {
+            kind: ErrorKind::NotImplied(vec![N(Id(1))]),
+            step: 1,
+        })
+    );
+    //a proof that stops short
+    let steps = vec![DratStep::Add(vec![P(Id(1))])];
+    assert_eq!(
+        checker::check_drat(&cnf, &steps).unwrap_err().kind,
+        ErrorKind::NoRefutation
+    );
+}
+
+#[test]
+fn check_lrat() {
+    let text = "5 2 0 1 2 0\n5 d 1 0\n6 0 5 3 4 0\n";
+    let steps = checker::read_lrat(text.as_bytes()).unwrap();
+    assert_eq!(
+        steps[0],
+        LratStep::Add {
+            id: 5,
+            cls: vec![P(Id(2))],
+            hints: vec![1, 2],
+        }
+    );
+    assert_eq!(checker::check_lrat(&all_pairs(), &steps), Ok(()));
+
+    //clause 4 isn't unit once 2 is made false, so the hint is wrong
+    let bad = checker::read_lrat("5 2 0 4 0\n".as_bytes()).unwrap();
+    assert_eq!(
+        checker::check_lrat(&all_pairs(), &bad).unwrap_err().kind,
+        ErrorKind::BadHint(4)
+    );
+}
+
+#[test]
+fn read_errors() {
+    let err = checker::read_drat("1 2 0\n1 x 0\n".as_bytes(), proof::Format::Text).unwrap_err();
+    assert_eq!(err.step, 2);
+    assert_eq!(
+        err.to_string(),
+        "invalid proof: `x` isn't a number at step 2"
+    );
+    let err = checker::read_lrat("5 2 0 -1 0\n".as_bytes()).unwrap_err();
+    assert_eq!(
+        err.kind,
+        ErrorKind::Syntax("RAT hints aren't supported".to_string())
+    );
+}
@@ -0,0 +1,35 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+//Run the binary with the args on the input, from stdin
+fn run(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sat"))
+        .args(args)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn max_models() {
+    let sat = "p cnf 1 1\n1 0\n";
+    //no models can't be told apart from UNSAT
+    let out = run(&["--max-models", "0"], sat);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--max-models"));
+    assert!(!String::from_utf8_lossy(&out.stdout).contains("s UNSATISFIABLE"));
+
+    let out = run(&["--max-models", "1"], sat);
+    assert_eq!(out.status.code(), Some(10));
+    assert!(String::from_utf8_lossy(&out.stdout).contains("s SATISFIABLE"));
+}
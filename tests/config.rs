@@ -0,0 +1,119 @@
+use sat::config::{Limits, SolverConfig};
+use sat::heuristic::{Branching, Phase};
+use sat::restart::Restart;
+use sat::{naive, nonchro, proof, SATSolver};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[test]
+fn builder() {
+    let config = SolverConfig::builder()
+        .branching(Branching::Vsids)
+        .restart(Restart::Glucose)
+        .phase(Phase::Random)
+        .seed(42)
+        .build();
+    assert_eq!(config.branching, Branching::Vsids);
+    assert_eq!(config.restart, Restart::Glucose);
+    assert_eq!(config.phase, Phase::Random);
+    assert_eq!(config.seed, 42);
+    //the rest keeps its defaults
+    assert_eq!(
+        config.reduce_interval,
+        SolverConfig::default().reduce_interval
+    );
+    assert_eq!(config.proof, None);
+}
+
+#[test]
+fn from_toml() {
+    let config = SolverConfig::from_toml(
+        r#"
+        branching = "order"
+        phase = "target"
+        restart = { geometric = { first = 50, factor = 2.0 } }
+        reduce_interval = 500
+
+        [proof]
+        path = "out.drat"
+        format = "binary"
+        "#,
+    )
+    .unwrap();
+    let expected = SolverConfig::builder()
+        .branching(Branching::Order)
+        .phase(Phase::Target)
+        .restart(Restart::Geometric {
+            first: 50,
+            factor: 2.0,
+        })
+        .reduce_interval(Some(500))
+        .proof("out.drat", proof::Format::Binary)
+        .build();
+    assert_eq!(config, expected);
+
+    assert!(SolverConfig::from_toml("restarts = \"never\"").is_err());
+}
+
+#[test]
+fn limits() {
+    let config = SolverConfig::from_toml(
+        r#"
+        [limits]
+        conflicts = 10000
+        time = 2.5
+        "#,
+    )
+    .unwrap();
+    let expected = Limits {
+        conflicts: Some(10000),
+        time: Some(Duration::from_millis(2500)),
+        ..Limits::default()
+    };
+    assert_eq!(config.limits, expected);
+
+    assert!(SolverConfig::from_toml("[limits]\ntime = -1").is_err());
+    assert!(SolverConfig::from_toml("[limits]\ndeadline = 1").is_err());
+}
+
+#[test]
+fn from_json() {
+    let config =
+        SolverConfig::from_json(r#"{"restart": {"luby": 10}, "reduce_interval": null, "seed": 3}"#)
+            .unwrap();
+    assert_eq!(config.restart, Restart::Luby(10));
+    assert_eq!(config.reduce_interval, None);
+    assert_eq!(config.seed, 3);
+    assert_eq!(config.proof, None);
+}
+
+#[test]
+fn restarts_need_conflicts() {
+    for restart in [Restart::Fixed(0), Restart::Luby(0)] {
+        let config = SolverConfig::builder().restart(restart).build();
+        assert!(nonchro::Solver::with_config(vec![], None, &config).is_err());
+    }
+    assert!(SolverConfig::from_toml("restart = { fixed = 0 }").is_err());
+    assert!(SolverConfig::from_toml("restart = { luby = 0 }").is_err());
+    assert!(
+        SolverConfig::from_toml("restart = { geometric = { first = 0, factor = 2.0 } }").is_err()
+    );
+    assert!(
+        SolverConfig::from_toml("restart = { geometric = { first = 10, factor = 0.5 } }").is_err()
+    );
+    assert!(SolverConfig::from_toml("restart = { fixed = 1 }").is_ok());
+}
+
+#[test]
+fn proof_needs_nonchro() {
+    let path: PathBuf = std::env::temp_dir().join("rust-sat-config-test.drat");
+    let config = SolverConfig::builder()
+        .proof(&path, proof::Format::Text)
+        .build();
+    let cnf = vec![];
+    assert!(naive::Solver::with_config(cnf.clone(), None, &config).is_err());
+    let mut solver = nonchro::Solver::with_config(cnf, None, &config).unwrap();
+    assert!(solver.solve().is_sat());
+    solver.finish_proof().unwrap();
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,115 @@
+use num_bigint::BigUint;
+use proptest::prelude::*;
+use sat::config::Limits;
+use sat::count::Counter;
+use sat::Lit::{N, P};
+use sat::{Id, Lit, CNF};
+
+mod assignment;
+
+fn count(cnf: CNF, vars: usize) -> BigUint {
+    Counter::new(cnf, vars).count().unwrap()
+}
+
+#[test]
+fn simple_counts() {
+    //vars in no clause can be anything
+    assert_eq!(count(Vec::new(), 3), BigUint::from(8u32));
+    assert_eq!(
+        count(vec![vec![P(Id(1)), P(Id(2))]], 3),
+        BigUint::from(6u32)
+    );
+    assert_eq!(
+        count(vec![vec![P(Id(1))], vec![N(Id(1))]], 1),
+        BigUint::ZERO
+    );
+    assert_eq!(count(vec![Vec::new()], 2), BigUint::ZERO);
+}
+
+#[test]
+fn unsat_counts_zero() {
+    //every clause over 1, 2 and 3, each ruling out one of their values
+    let cnf: CNF = (0..8)
+        .map(|bits| {
+            (1..=3)
+                .map(|id| {
+                    if bits & 1 << (id - 1) != 0 {
+                        P(Id(id))
+                    } else {
+                        N(Id(id))
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    assert_eq!(count(cnf, 3), BigUint::ZERO);
+}
+
+#[test]
+fn big_counts() {
+    //100 components of 1 or 2, far too many models to go through
+    let cnf: CNF = (0..100)
+        .map(|i| vec![P(Id(2 * i + 1)), P(Id(2 * i + 2))])
+        .collect();
+    assert_eq!(count(cnf, 200), BigUint::from(3u32).pow(100));
+}
+
+#[test]
+fn long_clause_counts() {
+    //one branch per var would go 40000 calls deep
+    let clause: Vec<Lit> = (1..=40000).map(|id| N(Id(id))).collect();
+    let mut counter = Counter::new(vec![clause.clone()], 40000);
+    counter.set_projection(&[Id(1), Id(2), Id(3)]);
+    assert_eq!(counter.count(), Ok(BigUint::from(8u32)));
+
+    let mut cnf = vec![clause];
+    cnf.push(vec![P(Id(1))]);
+    assert_eq!(count(cnf, 40000), (BigUint::from(1u32) << 39999) - 1u32);
+}
+
+#[test]
+fn projected_counts() {
+    //2 and 3 can be anything as long as 1 is
+    let cnf = vec![vec![P(Id(1)), P(Id(2))], vec![P(Id(1)), P(Id(3))]];
+    let mut counter = Counter::new(cnf, 4);
+    counter.set_projection(&[Id(2), Id(3), Id(4)]);
+    assert_eq!(counter.count(), Ok(BigUint::from(8u32)));
+    counter.set_projection(&[]);
+    assert_eq!(counter.count(), Ok(BigUint::from(1u32)));
+    //a var in no clause, even past the formula's, can be either
+    counter.set_projection(&[Id(1), Id(9)]);
+    assert_eq!(counter.count(), Ok(BigUint::from(4u32)));
+}
+
+#[test]
+fn stopped_counts() {
+    let mut counter = Counter::new(vec![vec![P(Id(1)), P(Id(2))]], 2);
+    counter.set_limits(Limits {
+        decisions: Some(0),
+        ..Limits::default()
+    });
+    assert_eq!(
+        counter.count(),
+        Err("Decision limit of 0 reached".to_string())
+    );
+
+    let mut counter = Counter::new(vec![vec![P(Id(1)), P(Id(2))]], 2);
+    counter.interrupt_handle().interrupt();
+    assert_eq!(counter.count(), Err("Interrupted".to_string()));
+}
+
+proptest! {
+    #[test]
+    fn same_as_brute_force(cnf in prop::collection::vec(prop::collection::vec(assignment::lit(), 1..4), 0..20)) {
+        let all: Vec<usize> = (1..=8).collect();
+        prop_assert_eq!(count(cnf.clone(), 8), BigUint::from(assignment::brute_force(&cnf, &all, &all).len()));
+
+        let projection = [1, 3, 4, 8];
+        let mut counter = Counter::new(cnf.clone(), 8);
+        counter.set_projection(&projection.iter().map(|&id| Id(id)).collect::<Vec<_>>());
+        prop_assert_eq!(
+            counter.count().unwrap(),
+            BigUint::from(assignment::brute_force(&cnf, &all, &projection).len())
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use sat::decompress;
+use sat::decompress::Format;
+use sat::parse;
+use std::fs::File;
+use std::io::BufReader;
+
+fn open(path: &str) -> BufReader<File> {
+    BufReader::new(File::open(path).unwrap())
+}
+
+fn read_fixture(path: &str) -> std::io::Result<sat::CNF> {
+    let input = decompress::decompress(open(path))?;
+    let (cnf, _) = parse::parse_reader(input, parse::Mode::Strict).unwrap();
+    Ok(cnf)
+}
+
+#[test]
+fn detect_format() {
+    let cases = vec![
+        ("tests/compressed/small.cnf", Format::Plain),
+        ("tests/compressed/small.cnf.gz", Format::Gzip),
+        ("tests/compressed/small.cnf.xz", Format::Xz),
+        ("tests/compressed/small.cnf.bz2", Format::Bzip2),
+    ];
+    for (path, format) in cases {
+        assert_eq!(decompress::detect(&mut open(path)).unwrap(), format);
+    }
+}
+
+#[test]
+fn read_plain() {
+    assert_eq!(
+        read_fixture("tests/compressed/small.cnf").unwrap().len(),
+        10
+    );
+}
+
+fn check_compressed(path: &str, enabled: bool) {
+    let plain = read_fixture("tests/compressed/small.cnf").unwrap();
+    match read_fixture(path) {
+        Ok(cnf) => assert!(enabled && cnf == plain),
+        Err(e) => assert!(!enabled && e.kind() == std::io::ErrorKind::Unsupported),
+    }
+}
+
+#[test]
+fn read_gzip() {
+    check_compressed("tests/compressed/small.cnf.gz", cfg!(feature = "gzip"))
+}
+
+#[test]
+fn read_xz() {
+    check_compressed("tests/compressed/small.cnf.xz", cfg!(feature = "xz"))
+}
+
+#[test]
+fn read_bzip2() {
+    check_compressed("tests/compressed/small.cnf.bz2", cfg!(feature = "bzip2"))
+}
@@ -0,0 +1,96 @@
+use proptest::prelude::*;
+use sat::config::Limits;
+use sat::nonchro::Solver;
+use sat::Lit::{N, P};
+use sat::{check, Id, Interp, Lit, SATSolver};
+use std::collections::HashSet;
+
+mod assignment;
+
+#[test]
+fn all_models() {
+    //1 or 2, and 3 unless 1
+    let cnf = vec![vec![P(Id(1)), P(Id(2))], vec![P(Id(1)), P(Id(3))]];
+    let mut solver = Solver::create(cnf.clone(), None);
+    let models: Vec<Interp> = solver.models(None).collect();
+    assert_eq!(models.len(), 5);
+    let distinct: HashSet<_> = models
+        .iter()
+        .map(|m| assignment::values(m, &[1, 2, 3]))
+        .collect();
+    assert_eq!(
+        distinct,
+        assignment::brute_force(&cnf, &[1, 2, 3], &[1, 2, 3])
+    );
+    assert!(models.iter().all(|m| check(&cnf, m)));
+    assert!(!solver.solve().is_sat());
+}
+
+#[test]
+fn projected_models() {
+    let cnf = vec![vec![P(Id(1)), P(Id(2))], vec![P(Id(1)), P(Id(3))]];
+    let mut solver = Solver::create(cnf, None);
+    //the same var twice is the same projection
+    let models: Vec<Interp> = solver.models(Some(vec![Id(2), Id(1), Id(2)])).collect();
+    let distinct: HashSet<_> = models
+        .iter()
+        .map(|m| assignment::values(m, &[1, 2]))
+        .collect();
+    assert_eq!(models.len(), 3);
+    assert_eq!(distinct.len(), 3);
+
+    //just the one projection onto nothing
+    let mut solver = Solver::create(vec![vec![P(Id(1)), P(Id(2))]], None);
+    assert_eq!(solver.models(Some(Vec::new())).count(), 1);
+}
+
+#[test]
+fn no_models() {
+    let mut solver = Solver::create(vec![vec![P(Id(1))], vec![N(Id(1))]], None);
+    let mut models = solver.models(None);
+    assert!(models.next().is_none());
+    assert!(models.next().is_none());
+    assert_eq!(models.unknown(), None);
+}
+
+#[test]
+fn stopped_models() {
+    let mut solver = Solver::create(vec![vec![P(Id(1)), P(Id(2))]], None);
+    solver.set_limits(Limits {
+        decisions: Some(0),
+        ..Limits::default()
+    });
+    let mut models = solver.models(None);
+    assert!(models.next().is_none());
+    assert_eq!(models.unknown(), Some("Decision limit of 0 reached"));
+}
+
+//Clauses with each var at most once
+fn clause() -> impl Strategy<Value = Vec<Lit>> {
+    prop::collection::vec(assignment::lit(), 1..4).prop_map(|mut cls| {
+        let mut seen = HashSet::new();
+        cls.retain(|l| seen.insert(l.id().0));
+        cls
+    })
+}
+
+proptest! {
+    #[test]
+    fn same_as_brute_force(cnf in prop::collection::vec(clause(), 1..20)) {
+        let vars = assignment::vars_of(&cnf);
+        let mut solver = Solver::create(cnf.clone(), None);
+        let models: Vec<Interp> = solver.models(None).collect();
+        let distinct: HashSet<_> = models.iter().map(|m| assignment::values(m, &vars)).collect();
+        prop_assert_eq!(distinct.len(), models.len());
+        prop_assert_eq!(distinct, assignment::brute_force(&cnf, &vars, &vars));
+
+        let projection: Vec<usize> = vars.iter().cloned().filter(|&id| id <= 3).collect();
+        let ids = projection.iter().map(|&id| Id(id)).collect();
+        let mut solver = Solver::create(cnf.clone(), None);
+        let models: Vec<Interp> = solver.models(Some(ids)).collect();
+        prop_assert!(models.iter().all(|m| check(&cnf, m)));
+        let distinct: HashSet<_> = models.iter().map(|m| assignment::values(m, &projection)).collect();
+        prop_assert_eq!(distinct.len(), models.len());
+        prop_assert_eq!(distinct, assignment::brute_force(&cnf, &vars, &projection));
+    }
+}
@@ -16,3 +16,23 @@ fn test_solve_sat() {
 fn test_solve_unsat() {
     satsolver::test_solve_unsat::<Solver>()
 }
+
+#[test]
+fn test_limits() {
+    satsolver::test_limits::<Solver>()
+}
+
+#[test]
+fn test_interrupt() {
+    satsolver::test_interrupt::<Solver>()
+}
+
+#[test]
+fn test_stats() {
+    satsolver::test_stats::<Solver>()
+}
+
+#[test]
+fn test_progress() {
+    satsolver::test_progress::<Solver>()
+}
@@ -1,7 +1,20 @@
+use sat::config::{Limits, SolverConfig};
+use sat::heuristic::{Branching, Phase};
 use sat::nonchro::Solver;
+use sat::restart::Restart;
+use sat::Lit::{N, P};
+use sat::{check, checker, proof, Id, SATSolver, Satness, CNF};
+use std::cell::RefCell;
+use std::io;
+use std::io::Write;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
 
 mod satsolver;
 
+use satsolver::pigeonhole;
+
 #[test]
 fn test_solve_simple() {
     satsolver::test_solve_simple::<Solver>()
@@ -16,3 +29,341 @@ fn test_solve_sat() {
 fn test_solve_unsat() {
     satsolver::test_solve_unsat::<Solver>()
 }
+
+#[test]
+fn test_proof_unsat() {
+    satsolver::test_proof_unsat(solve_with_proof)
+}
+
+//Collects the proof so the test can read it back after the solver is done
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn solve_with_proof(cnf: CNF) -> (Satness, Vec<u8>) {
+    let out = Shared::default();
+    let mut solver = Solver::create(cnf, None);
+    solver.log_proof(out.clone(), proof::Format::Text);
+    let solvable = solver.solve();
+    solver.finish_proof().unwrap();
+    let written = out.0.borrow().clone();
+    (solvable, written)
+}
+
+#[test]
+fn test_proof() {
+    //every assignment of 1 and 2 is ruled out
+    let cnf = vec![
+        vec![P(Id(1)), P(Id(2))],
+        vec![P(Id(1)), N(Id(2))],
+        vec![N(Id(1)), P(Id(2))],
+        vec![N(Id(1)), N(Id(2))],
+    ];
+    let (solvable, written) = solve_with_proof(cnf.clone());
+    match solvable {
+        Satness::UNSAT(_) => {}
+        res => panic!("expected UNSAT, got {:?}", res),
+    }
+    let steps = checker::read_drat(&written[..], proof::Format::Text).unwrap();
+    assert_eq!(steps.last(), Some(&checker::DratStep::Add(vec![])));
+    assert_eq!(checker::check_drat(&cnf, &steps), Ok(()));
+    //without the last clause the rest is SAT, so the proof can't hold
+    assert!(checker::check_drat(&cnf[..3].to_vec(), &steps).is_err());
+}
+
+#[test]
+fn test_branching() {
+    for branching in [Branching::Order, Branching::Vsids, Branching::Evsids] {
+        let mut solver = Solver::create(pigeonhole(4), None);
+        solver.set_branching(branching);
+        assert!(!solver.solve().is_sat());
+
+        //with a hole for every pigeon
+        let cnf = pigeonhole(4)[1..].to_vec();
+        let mut solver = Solver::create(cnf.clone(), None);
+        solver.set_branching(branching);
+        match solver.solve() {
+            Satness::SAT(interp) => assert!(check(&cnf, &interp)),
+            res => panic!("expected SAT, got {:?}", res),
+        }
+    }
+}
+
+#[test]
+fn test_restart() {
+    let policies = [
+        Restart::Never,
+        Restart::Fixed(1),
+        Restart::Luby(1),
+        Restart::Geometric {
+            first: 1,
+            factor: 1.5,
+        },
+        Restart::Glucose,
+    ];
+    for policy in policies {
+        let mut solver = Solver::create(pigeonhole(5), None);
+        solver.set_restart(policy);
+        assert!(!solver.solve().is_sat());
+
+        //restarting mustn't lose the assumptions
+        let cnf = pigeonhole(5)[1..].to_vec();
+        let mut solver = Solver::create(cnf.clone(), None);
+        solver.set_restart(policy);
+        match solver.solve_with_assumptions(&[N(Id(1))]) {
+            Satness::SAT(interp) => {
+                assert!(check(&cnf, &interp));
+                assert_eq!(interp.get_val(&P(Id(1))), Some(false));
+            }
+            res => panic!("expected SAT, got {:?}", res),
+        }
+    }
+}
+
+#[test]
+fn test_phase() {
+    //any values for 1 and 3 will do
+    let cnf = vec![vec![P(Id(1)), N(Id(2))], vec![P(Id(3)), N(Id(4))]];
+    for (phase, value) in [(Phase::False, false), (Phase::True, true)] {
+        let mut solver = Solver::create(cnf.clone(), None);
+        solver.set_phase(phase, 0);
+        match solver.solve() {
+            Satness::SAT(interp) => {
+                for var in 1..=4 {
+                    assert_eq!(interp.get_val(&P(Id(var))), Some(value));
+                }
+            }
+            res => panic!("expected SAT, got {:?}", res),
+        }
+    }
+
+    for phase in [Phase::Saved, Phase::Random, Phase::Target] {
+        let config = SolverConfig {
+            phase,
+            seed: 7,
+            ..Default::default()
+        };
+        let mut solver = Solver::create(pigeonhole(5), None);
+        solver.configure(&config);
+        assert!(!solver.solve().is_sat());
+
+        let cnf = pigeonhole(5)[1..].to_vec();
+        let mut solver = Solver::create(cnf.clone(), None);
+        solver.configure(&config);
+        match solver.solve() {
+            Satness::SAT(interp) => assert!(check(&cnf, &interp)),
+            res => panic!("expected SAT, got {:?}", res),
+        }
+    }
+}
+
+#[test]
+fn test_reduce_db() {
+    let cnf = pigeonhole(6);
+    let out = Shared::default();
+    let mut solver = Solver::create(cnf.clone(), None);
+    solver.set_reduce_interval(Some(10));
+    solver.log_proof(out.clone(), proof::Format::Text);
+    assert!(!solver.solve().is_sat());
+    solver.finish_proof().unwrap();
+
+    //the deleted clauses show up in the proof, which must still hold
+    let steps = checker::read_drat(&out.0.borrow()[..], proof::Format::Text).unwrap();
+    assert!(steps
+        .iter()
+        .any(|step| matches!(step, checker::DratStep::Delete(_))));
+    assert_eq!(checker::check_drat(&cnf, &steps), Ok(()));
+
+    //reducing mustn't lose models either
+    let cnf = pigeonhole(6)[1..].to_vec();
+    let mut solver = Solver::create(cnf.clone(), None);
+    solver.set_reduce_interval(Some(10));
+    match solver.solve() {
+        Satness::SAT(interp) => assert!(check(&cnf, &interp)),
+        res => panic!("expected SAT, got {:?}", res),
+    }
+}
+
+#[test]
+fn test_solve_with_assumptions() {
+    //1 -> 2, 2 -> 3, 4 is free
+    let cnf = vec![
+        vec![N(Id(1)), P(Id(2))],
+        vec![N(Id(2)), P(Id(3))],
+        vec![P(Id(4)), P(Id(5))],
+    ];
+    let mut solver = Solver::create(cnf.clone(), None);
+
+    match solver.solve_with_assumptions(&[P(Id(1)), N(Id(4))]) {
+        Satness::SAT(interp) => {
+            assert!(check(&cnf, &interp));
+            assert_eq!(interp.get_val(&P(Id(3))), Some(true));
+            assert_eq!(interp.get_val(&P(Id(5))), Some(true));
+        }
+        res => panic!("expected SAT, got {:?}", res),
+    }
+
+    //only 1 and -3 are needed for the conflict
+    match solver.solve_with_assumptions(&[N(Id(4)), P(Id(1)), N(Id(3))]) {
+        Satness::UNSATAssuming(mut failed) => {
+            failed.sort_by_key(|l| l.as_usize());
+            assert_eq!(failed, vec![P(Id(1)), N(Id(3))]);
+        }
+        res => panic!("expected UNSAT under assumptions, got {:?}", res),
+    }
+
+    //without assumptions it is still SAT
+    assert!(solver.solve().is_sat());
+}
+
+#[test]
+fn test_add_clause() {
+    let mut cnf = vec![vec![P(Id(1)), P(Id(2))], vec![N(Id(1)), P(Id(3))]];
+    let mut solver = Solver::create(cnf.clone(), None);
+    assert!(solver.solve().is_sat());
+
+    for cls in [vec![N(Id(3))], vec![N(Id(2)), P(Id(4))]] {
+        cnf.push(cls.clone());
+        solver.add_clause(cls);
+        match solver.solve() {
+            Satness::SAT(interp) => assert!(check(&cnf, &interp)),
+            res => panic!("expected SAT, got {:?}", res),
+        }
+    }
+
+    solver.add_clause(vec![N(Id(4))]);
+    match solver.solve() {
+        Satness::UNSAT(_) => {}
+        res => panic!("expected UNSAT, got {:?}", res),
+    }
+    //once UNSAT, no assumptions can help
+    match solver.solve_with_assumptions(&[P(Id(1))]) {
+        Satness::UNSAT(_) => {}
+        res => panic!("expected UNSAT, got {:?}", res),
+    }
+}
+
+#[test]
+fn test_unsat_core() {
+    //1 and 2 are forced and clash through 3, 4 and 5 are just noise
+    let cnf = vec![
+        vec![P(Id(4)), P(Id(5))],
+        vec![P(Id(1))],
+        vec![N(Id(1)), P(Id(3))],
+        vec![N(Id(4)), N(Id(5))],
+        vec![N(Id(3)), N(Id(2))],
+        vec![P(Id(2))],
+    ];
+    let mut solver = Solver::create(cnf, None);
+    solver.track_core();
+    assert!(!solver.solve().is_sat());
+    assert_eq!(solver.unsat_core(), Some(&[1, 2, 4, 5][..]));
+
+    //under assumptions only the clauses that refute them are in the core
+    let cnf = vec![
+        vec![N(Id(1)), P(Id(2))],
+        vec![P(Id(4)), P(Id(5))],
+        vec![N(Id(2)), P(Id(3))],
+    ];
+    let mut solver = Solver::create(cnf, None);
+    solver.track_core();
+    match solver.solve_with_assumptions(&[P(Id(1)), N(Id(3))]) {
+        Satness::UNSATAssuming(_) => {}
+        res => panic!("expected UNSAT under assumptions, got {:?}", res),
+    }
+    assert_eq!(solver.unsat_core(), Some(&[0, 2][..]));
+
+    //added clauses are numbered after the ones given to create
+    solver.add_clause(vec![N(Id(4))]);
+    solver.add_clause(vec![N(Id(5))]);
+    assert!(!solver.solve().is_sat());
+    assert_eq!(solver.unsat_core(), Some(&[1, 3, 4][..]));
+}
+
+#[test]
+fn test_limits() {
+    satsolver::test_limits::<Solver>()
+}
+
+#[test]
+fn test_interrupt() {
+    satsolver::test_interrupt::<Solver>()
+}
+
+#[test]
+fn test_stats() {
+    satsolver::test_stats::<Solver>()
+}
+
+#[test]
+fn test_progress() {
+    satsolver::test_progress::<Solver>()
+}
+
+#[test]
+fn test_limits_resume() {
+    //stopping leaves the learned clauses for the next call to carry on with
+    let limited = Limits {
+        conflicts: Some(10),
+        ..Limits::default()
+    };
+    let mut solver = Solver::create(pigeonhole(6), None);
+    solver.set_limits(limited);
+    assert!(solver.solve().is_unknown());
+    solver.set_limits(Limits::default());
+    assert!(matches!(solver.solve(), Satness::UNSAT(_)));
+
+    let cnf = pigeonhole(6)[1..].to_vec();
+    let mut solver = Solver::create(cnf.clone(), None);
+    solver.set_limits(Limits {
+        decisions: Some(2),
+        ..Limits::default()
+    });
+    assert!(solver.solve().is_unknown());
+    solver.set_limits(Limits::default());
+    match solver.solve() {
+        Satness::SAT(interp) => assert!(check(&cnf, &interp)),
+        e => panic!("{:?}", e),
+    }
+}
+
+#[test]
+fn test_interrupt_thread() {
+    //far too hard to finish before it's stopped
+    let mut solver = Solver::create(pigeonhole(10), None);
+    let handle = solver.interrupt_handle();
+    let stopper = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        handle.interrupt();
+    });
+    assert!(solver.solve().is_unknown());
+    stopper.join().unwrap();
+}
+
+#[test]
+fn test_learned_stats() {
+    let mut solver = Solver::create(pigeonhole(5), None);
+    assert!(matches!(solver.solve(), Satness::UNSAT(_)));
+    let stats = *solver.stats();
+    //all but the last conflict, which is at the root
+    assert_eq!(stats.learned, stats.conflicts - 1);
+    assert!(stats.avg_learned_len() >= 1.0);
+    assert!(stats.avg_backjump() >= 1.0);
+
+    //they add up over the calls
+    let mut solver = Solver::create(pigeonhole(5)[1..].to_vec(), None);
+    assert!(solver.solve().is_sat());
+    let first = *solver.stats();
+    assert!(solver.solve().is_sat());
+    assert!(solver.stats().decisions > first.decisions);
+    assert!(solver.stats().propagations >= first.propagations);
+}
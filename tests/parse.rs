@@ -1,4 +1,5 @@
 use sat::parse;
+use sat::parse::ErrorKind;
 use sat::Id;
 use sat::Lit::{N, P};
 use std::fs;
@@ -8,7 +9,7 @@ fn parse_file() {
     match fs::read_to_string("./tests/uf20-91/uf20-0101.cnf") {
         Ok(s) => {
             let parsed = parse::parse_file(s);
-            assert_eq!(parsed.is_ok(), true);
+            assert!(parsed.is_ok());
         }
         Err(e) => panic!("read error: {}", e),
     }
@@ -30,3 +31,109 @@ fn parse_lit() {
     let parsed = parse::parse_lit("-22".to_string());
     assert_eq!(control, parsed.unwrap());
 }
+
+#[test]
+fn parse_header() {
+    let (cnf, header) =
+        parse::parse_file("c comment\np cnf 3 2\n1 -3 0\n2 3 -1 0\n".to_string()).unwrap();
+    assert_eq!(
+        header,
+        Some(parse::Header {
+            vars: 3,
+            clauses: 2
+        })
+    );
+    assert_eq!(cnf.len(), 2);
+}
+
+#[test]
+fn parse_header_mismatch() {
+    let truncated = "p cnf 3 3\n1 -3 0\n2 3 -1 0\n".to_string();
+    let err = parse::parse_file(truncated.clone()).unwrap_err();
+    assert_eq!(
+        err.kind,
+        ErrorKind::ClauseCount {
+            declared: 3,
+            found: 2
+        }
+    );
+    let (cnf, _) = parse::parse_file_with(truncated, parse::Mode::Lenient).unwrap();
+    assert_eq!(cnf.len(), 2);
+
+    let undeclared = "p cnf 2 1\n1 -3 0\n".to_string();
+    let err = parse::parse_file(undeclared).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::VarOutOfRange { declared: 2 });
+    assert_eq!((err.line, err.col, &err.token[..]), (2, 3, "-3"));
+}
+
+#[test]
+fn parse_error_position() {
+    let cases = vec![
+        ("1 2 0\n3 4x 0\n", ErrorKind::BadLiteral, 2, 3, "4x"),
+        ("1 2 0\n  - 0\n", ErrorKind::BadLiteral, 2, 3, "-"),
+        ("1 2 0\n3 -0\n", ErrorKind::BadLiteral, 2, 3, "-0"),
+        ("c ok\n1 y 0\n", ErrorKind::UnexpectedChar, 2, 3, "y"),
+        ("1 2 0\n3 4\n", ErrorKind::MissingTerminator, 3, 1, ""),
+        (
+            "1 99999999999999999999999 0\n",
+            ErrorKind::IdOverflow,
+            1,
+            3,
+            "99999999999999999999999",
+        ),
+        ("p cnf 2\n1 2 0\n", ErrorKind::Header, 1, 1, "p cnf 2"),
+    ];
+    for (input, kind, line, col, token) in cases {
+        let err = parse::parse_file(input.to_string()).unwrap_err();
+        assert_eq!(err.kind, kind, "{:?}", input);
+        assert_eq!((err.line, err.col, &err.token[..]), (line, col, token));
+    }
+}
+
+#[test]
+fn parse_error_display() {
+    let err = parse::parse_file("1 2 0\n3 4x 0\n".to_string()).unwrap_err();
+    assert_eq!(err.to_string(), "invalid literal `4x` at line 2, column 3");
+}
+
+#[test]
+fn read_incrementally() {
+    let input = "c streamed\np cnf 3 2\n1 -3 0\n2 3\n -1 0\n".as_bytes();
+    let mut reader = parse::Reader::new(input);
+    assert_eq!(reader.next().unwrap().unwrap(), vec![P(Id(1)), N(Id(3))]);
+    assert_eq!(
+        reader.header(),
+        Some(parse::Header {
+            vars: 3,
+            clauses: 2
+        })
+    );
+    assert_eq!(
+        reader.next().unwrap().unwrap(),
+        vec![P(Id(2)), P(Id(3)), N(Id(1))]
+    );
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn read_stops_at_error() {
+    let input = "p cnf 3 3\n1 -3 0\n2 3 -1 0\n".as_bytes();
+    let results: Vec<_> = parse::Reader::new(input).collect();
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok() && results[1].is_ok());
+    assert_eq!(
+        results[2].as_ref().unwrap_err().kind,
+        ErrorKind::ClauseCount {
+            declared: 3,
+            found: 2
+        }
+    );
+}
+
+#[test]
+fn read_invalid_utf8() {
+    let input: &[u8] = b"p cnf 2 1\n1 \xff 0\n";
+    let err = parse::parse_reader(input, parse::Mode::Strict).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Io(std::io::ErrorKind::InvalidData));
+    assert_eq!(err.line, 2);
+}
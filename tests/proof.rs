@@ -0,0 +1,37 @@
+use sat::Lit::{N, P};
+use sat::{proof, Id, Lit};
+
+fn write_proof(format: proof::Format, steps: &[(bool, Vec<Lit>)]) -> Vec<u8> {
+    let mut writer = proof::Writer::new(Vec::new(), format);
+    for (add, cls) in steps {
+        if *add {
+            writer.add(cls);
+        } else {
+            writer.delete(cls);
+        }
+    }
+    writer.finish().unwrap()
+}
+
+#[test]
+fn text_proof() {
+    let steps = vec![
+        (true, vec![P(Id(1)), N(Id(2))]),
+        (false, vec![N(Id(3))]),
+        (true, vec![]),
+    ];
+    let out = write_proof(proof::Format::Text, &steps);
+    assert_eq!(String::from_utf8(out).unwrap(), "1 -2 0\nd -3 0\n0\n");
+}
+
+#[test]
+fn binary_proof() {
+    let steps = vec![
+        (true, vec![P(Id(1)), N(Id(2))]),
+        (false, vec![N(Id(100))]),
+        (true, vec![]),
+    ];
+    let out = write_proof(proof::Format::Binary, &steps);
+    //-100 is 201, which takes two bytes
+    assert_eq!(out, vec![b'a', 2, 5, 0, b'd', 0xc9, 0x01, 0, b'a', 0]);
+}
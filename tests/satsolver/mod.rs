@@ -1,8 +1,12 @@
 use glob::glob;
-use sat::parse;
+use sat::config::{Limits, SolverConfig};
+use sat::stats::{Interval, Stats};
 use sat::Lit::{N, P};
-use sat::{check, Id, SATSolver, Satness};
+use sat::{check, Id, SATSolver, Satness, CNF};
+use sat::{checker, parse, proof};
 use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 pub fn test_solve_simple<Solver: SATSolver>() {
     let cnf1 = vec![vec![P(Id(1)), N(Id(2))], vec![N(Id(1))]];
@@ -10,14 +14,14 @@ pub fn test_solve_simple<Solver: SATSolver>() {
     let mut state1: Solver = SATSolver::create(cnf1, None);
     let ans1 = state1.solve();
     println!("{:?}", ans1);
-    assert_eq!(ans1.is_sat(), true);
+    assert!(ans1.is_sat());
 
     let cnf2 = vec![vec![P(Id(1))], vec![N(Id(1))]];
     println!("Test 2: {:?}", cnf2);
     let mut state2: Solver = SATSolver::create(cnf2, None);
     let ans2 = state2.solve();
     println!("{:?}", ans2);
-    assert_eq!(ans2.is_sat(), false);
+    assert!(!ans2.is_sat());
 
     let cnf3 = vec![
         vec![N(Id(1)), P(Id(1))],
@@ -28,7 +32,7 @@ pub fn test_solve_simple<Solver: SATSolver>() {
     let mut state3: Solver = SATSolver::create(cnf3, None);
     let ans3 = state3.solve();
     println!("{:?}", ans3);
-    assert_eq!(ans3.is_sat(), true);
+    assert!(ans3.is_sat());
 
     let cnf4 = vec![
         vec![N(Id(1)), P(Id(1))],
@@ -41,27 +45,30 @@ pub fn test_solve_simple<Solver: SATSolver>() {
     let mut state4: Solver = SATSolver::create(cnf4, None);
     let ans4 = state4.solve();
     println!("{:?}", ans4);
-    assert_eq!(ans4.is_sat(), true);
+    assert!(ans4.is_sat());
 }
 
 pub fn test_solve_file<Solver: SATSolver>(path: &str, sat: bool) {
     //for path in glob("tests/uf50-218/*.cnf").unwrap() {
     //for path in glob("tests/uf100-430/uf100-010.cnf").unwrap() {
     //for path in glob("tests/uf125-538/uf125-010.cnf").unwrap() {
-    for path in glob(path).unwrap() {
+    let paths: Vec<_> = glob(path).unwrap().collect();
+    assert!(!paths.is_empty(), "no files match {}", path);
+    for path in paths {
         //for path in glob("tests/uf175-753/uf175-010.cnf").unwrap() {
         //for path in glob("tests/sat/uf20-0584.cnf").unwrap() {
         println!("{:?}", &path);
-        let s = fs::read_to_string(&path.unwrap()).unwrap();
+        let s = fs::read_to_string(path.unwrap()).unwrap();
         match parse::parse_file(s) {
-            Ok(cnf) => {
+            Ok((cnf, _)) => {
                 let mut solver: Solver = SATSolver::create(cnf.clone(), None);
                 let solvable = solver.solve();
                 assert_eq!(solvable.is_sat(), sat);
                 if sat {
                     match solvable {
-                        Satness::UNSAT(_) => panic!("UNSAT"),
-                        Satness::SAT(interp) => assert_eq!(check(&cnf, &interp), true),
+                        Satness::UNSAT(_) | Satness::UNSATAssuming(_) => panic!("UNSAT"),
+                        Satness::UNKNOWN(reason) => panic!("UNKNOWN: {}", reason),
+                        Satness::SAT(interp) => assert!(check(&cnf, &interp)),
                     }
                 }
             }
@@ -78,6 +85,133 @@ pub fn test_solve_sat<Solver: SATSolver>() {
 }
 
 pub fn test_solve_unsat<Solver: SATSolver>() {
-    let _path50 = "tests/uuf50-218/*.cnf";
-    //test_solve_file::<Solver>(path50, false)
+    let path50 = "tests/uuf50-218/*.cnf";
+    test_solve_file::<Solver>(path50, false)
+}
+
+//Check the text DRAT proof given with each UNSAT answer with the built-in
+//checker, for solvers that can write one
+#[allow(dead_code)]
+pub fn test_proof_unsat(prove: fn(CNF) -> (Satness, Vec<u8>)) {
+    let paths: Vec<_> = glob("tests/uuf50-218/*.cnf").unwrap().collect();
+    assert!(!paths.is_empty(), "no UNSAT files to prove");
+    for path in paths {
+        println!("{:?}", &path);
+        let s = fs::read_to_string(path.unwrap()).unwrap();
+        let (cnf, _) = parse::parse_file(s).unwrap();
+        let (solvable, written) = prove(cnf.clone());
+        assert!(!solvable.is_sat());
+        let steps = checker::read_drat(&written[..], proof::Format::Text).unwrap();
+        assert_eq!(checker::check_drat(&cnf, &steps), Ok(()));
+    }
+}
+
+//n + 1 pigeons in n holes, var (p - 1) * n + h is pigeon p in hole h
+pub fn pigeonhole(n: usize) -> CNF {
+    let var = |p: usize, h: usize| Id((p - 1) * n + h);
+    let mut cnf: CNF = (1..=n + 1)
+        .map(|p| (1..=n).map(|h| P(var(p, h))).collect())
+        .collect();
+    for h in 1..=n {
+        for p in 1..=n + 1 {
+            for q in p + 1..=n + 1 {
+                cnf.push(vec![N(var(p, h)), N(var(q, h))]);
+            }
+        }
+    }
+    cnf
+}
+
+//Each limit stops the search with UNKNOWN, and limits that aren't reached
+//change nothing
+pub fn test_limits<Solver: SATSolver>() {
+    let limited = [
+        (
+            Limits {
+                conflicts: Some(1),
+                ..Limits::default()
+            },
+            "Conflict",
+        ),
+        (
+            Limits {
+                decisions: Some(2),
+                ..Limits::default()
+            },
+            "Decision",
+        ),
+        (
+            Limits {
+                propagations: Some(3),
+                ..Limits::default()
+            },
+            "Propagation",
+        ),
+        (
+            Limits {
+                time: Some(Duration::ZERO),
+                ..Limits::default()
+            },
+            "Time",
+        ),
+    ];
+    for (limits, reason) in limited {
+        let config = SolverConfig::builder().limits(limits).build();
+        let mut solver = Solver::with_config(pigeonhole(4), None, &config).unwrap();
+        match solver.solve() {
+            Satness::UNKNOWN(ref why) => assert!(why.starts_with(reason), "{}", why),
+            e => panic!("{:?}", e),
+        }
+    }
+
+    let mut solver: Solver = SATSolver::create(pigeonhole(3), None);
+    solver.set_limits(Limits {
+        conflicts: Some(1_000_000),
+        time: Some(Duration::from_secs(60)),
+        ..Limits::default()
+    });
+    assert!(matches!(solver.solve(), Satness::UNSAT(_)));
+}
+
+//An interrupt from any clone of the handle stops the search until it's
+//cleared
+pub fn test_interrupt<Solver: SATSolver>() {
+    let mut solver: Solver = SATSolver::create(pigeonhole(3), None);
+    let handle = solver.interrupt_handle();
+    handle.clone().interrupt();
+    match solver.solve() {
+        Satness::UNKNOWN(ref why) => assert_eq!(why, "Interrupted"),
+        e => panic!("{:?}", e),
+    }
+    assert!(handle.is_interrupted());
+    handle.clear();
+    assert!(matches!(solver.solve(), Satness::UNSAT(_)));
+}
+
+//Proving there's no room for 4 pigeons in 3 holes takes some of everything
+pub fn test_stats<Solver: SATSolver>() {
+    let mut solver: Solver = SATSolver::create(pigeonhole(3), None);
+    assert_eq!(*solver.stats(), Stats::default());
+    assert!(matches!(solver.solve(), Satness::UNSAT(_)));
+    let stats = solver.stats();
+    assert!(stats.decisions > 0);
+    assert!(stats.propagations > 0);
+    assert!(stats.conflicts > 0);
+    assert!(stats.backjumped > 0);
+    assert!(stats.max_level > 0 && stats.max_level <= 12);
+    assert!(stats.propagate_time > Duration::ZERO);
+}
+
+pub fn test_progress<Solver: SATSolver>() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut solver: Solver = SATSolver::create(pigeonhole(4), None);
+    let hook_seen = seen.clone();
+    solver.on_progress(Interval::Conflicts(5), move |stats| {
+        hook_seen.lock().unwrap().push(stats.conflicts)
+    });
+    assert!(matches!(solver.solve(), Satness::UNSAT(_)));
+    let seen = seen.lock().unwrap();
+    assert!(!seen.is_empty());
+    assert!(seen.windows(2).all(|w| w[1] >= w[0] + 5));
+    assert!(seen.iter().all(|&c| c <= solver.stats().conflicts));
 }
@@ -1,4 +1,5 @@
 use sat::watch::Solver;
+use sat::{parse, SATSolver};
 
 mod satsolver;
 
@@ -16,3 +17,32 @@ fn test_solve_sat() {
 fn test_solve_unsat() {
     satsolver::test_solve_unsat::<Solver>()
 }
+
+#[test]
+fn test_limits() {
+    satsolver::test_limits::<Solver>()
+}
+
+#[test]
+fn test_interrupt() {
+    satsolver::test_interrupt::<Solver>()
+}
+
+#[test]
+fn test_stats() {
+    satsolver::test_stats::<Solver>()
+}
+
+#[test]
+fn test_progress() {
+    satsolver::test_progress::<Solver>()
+}
+
+#[test]
+fn test_unsat_after_flip() {
+    //the flipped decision conflicts as well
+    let (cnf, _) =
+        parse::parse_file("p cnf 3 4\n1 0\n-3 0\n-2 -2 -1 0\n2 3 2 0\n".to_string()).unwrap();
+    let mut solver = Solver::create(cnf, None);
+    assert!(!solver.solve().is_sat());
+}
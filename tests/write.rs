@@ -0,0 +1,40 @@
+use proptest::prelude::*;
+use sat::Lit::{N, P};
+use sat::{parse, write, Id, Lit, CNF};
+use std::io;
+
+fn write_string(cnf: &CNF, comments: &[&str]) -> String {
+    let mut out = Vec::new();
+    write::write_file(&mut out, cnf, comments).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn write_file() {
+    let cnf = vec![vec![P(Id(1)), N(Id(3))], vec![N(Id(2))]];
+    assert_eq!(
+        write_string(&cnf, &["generated", "two lines\nof comments"]),
+        "c generated\nc two lines\nc of comments\np cnf 3 2\n1 -3 0\n-2 0\n"
+    );
+}
+
+fn lit() -> impl Strategy<Value = Lit> {
+    (1..50usize, any::<bool>()).prop_map(|(id, pos)| if pos { P(Id(id)) } else { N(Id(id)) })
+}
+
+proptest! {
+    #[test]
+    fn round_trip(cnf in prop::collection::vec(prop::collection::vec(lit(), 0..8), 0..30)) {
+        if cnf.iter().any(|cls| cls.is_empty()) {
+            let mut out = Vec::new();
+            let e = write::write_file(&mut out, &cnf, &[]).unwrap_err();
+            prop_assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+            prop_assert!(out.is_empty());
+            return Ok(());
+        }
+        let written = write_string(&cnf, &["round trip"]);
+        let (parsed, header) = parse::parse_file(written).unwrap();
+        prop_assert_eq!(header, Some(write::header_of(&cnf)));
+        prop_assert_eq!(parsed, cnf);
+    }
+}